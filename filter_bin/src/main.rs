@@ -1,6 +1,104 @@
-use filter_lib::*;
+use filter_lib::encoding::{self, DecodedText};
+use filter_lib::mode_parsing::{self, ParseOutput};
+use filter_lib::{format, printer};
+use std::{env, fs, process};
+
+const USAGE: &str = "usage: filter_bin fmt [--check] <file.filter>...
+       filter_bin json <file.filter>
+       filter_bin from-json <file.json>
+       filter_bin schema";
 
 fn main() {
-    println!("Hello, world!");
-    
+    let args: Vec<String> = env::args().skip(1).collect();
+    let code = match args.first().map(String::as_str) {
+        Some("fmt") => fmt(&args[1..]),
+        Some("json") if args.len() == 2 => json(&args[1]),
+        Some("from-json") if args.len() == 2 => from_json(&args[1]),
+        Some("schema") if args.len() == 1 => {
+            let schema = mode_parsing::json_schema();
+            println!("{}", serde_json::to_string_pretty(&schema).unwrap());
+            0
+        }
+        _ => {
+            eprintln!("{}", USAGE);
+            2
+        }
+    };
+    process::exit(code);
+}
+
+/// Reformats each file in place, or with `--check` only reports the files
+/// that are not formatted and exits non-zero if there are any. Files are
+/// written back in the encoding and line endings they were read in.
+fn fmt(args: &[String]) -> i32 {
+    let check = args.iter().any(|a| a == "--check");
+    let files: Vec<&String> = args.iter().filter(|a| *a != "--check").collect();
+    if files.is_empty() {
+        eprintln!("{}", USAGE);
+        return 2;
+    }
+    let mut code = 0;
+    for file in files {
+        let source = match read(file) {
+            Ok(source) => source,
+            Err(e) => {
+                eprintln!("{}: {}", file, e);
+                code = 2;
+                continue;
+            }
+        };
+        let formatted = format::format(&source.text);
+        if formatted == source.text {
+            continue;
+        }
+        if check {
+            println!("{} is not formatted", file);
+            code = code.max(1);
+        } else if let Err(e) = fs::write(file, source.encode(&formatted)) {
+            eprintln!("{}: {}", file, e);
+            code = 2;
+        }
+    }
+    code
+}
+
+/// Prints the parse of a filter as JSON.
+fn json(file: &str) -> i32 {
+    let source = match read(file) {
+        Ok(source) => source,
+        Err(e) => {
+            eprintln!("{}: {}", file, e);
+            return 2;
+        }
+    };
+    let output = mode_parsing::parse(&source.text);
+    println!("{}", serde_json::to_string_pretty(&output).unwrap());
+    0
+}
+
+/// Prints the filter text of a parse read back from JSON.
+fn from_json(file: &str) -> i32 {
+    let json = match fs::read_to_string(file) {
+        Ok(json) => json,
+        Err(e) => {
+            eprintln!("{}: {}", file, e);
+            return 2;
+        }
+    };
+    match serde_json::from_str::<ParseOutput>(&json) {
+        Ok(output) => {
+            print!("{}", printer::print(&output.blocks));
+            0
+        }
+        Err(e) => {
+            eprintln!("{}: {}", file, e);
+            1
+        }
+    }
+}
+
+/// Reads a filter file in whatever encoding and line endings it has.
+fn read(file: &str) -> Result<DecodedText, String> {
+    let bytes = fs::read(file).map_err(|e| e.to_string())?;
+    encoding::decode(&bytes).map_err(|e| e.to_string())
 }
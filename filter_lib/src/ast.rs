@@ -0,0 +1,771 @@
+pub use crate::color::Rgba;
+use crate::diagnostics::{Diagnostic, Severity};
+use crate::mode_parsing::{FilterBlock, Token, TokenAndSpan, ValueAndSpan};
+use std::ops::Range;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum Op {
+    Eq,
+    Exact,
+    NotEq,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum Rarity {
+    Normal,
+    Magic,
+    Rare,
+    Unique,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum Influence {
+    Shaper,
+    Elder,
+    Crusader,
+    Hunter,
+    Redeemer,
+    Warlord,
+    None,
+}
+
+/// A socket requirement such as `5`, `RGB` or `5RRG`.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct SocketSpec {
+    pub count: Option<u32>,
+    pub red: u32,
+    pub green: u32,
+    pub blue: u32,
+    pub white: u32,
+    pub abyss: u32,
+    pub delve: u32,
+}
+
+/// `MinimapIcon` sizes, `0` being the largest. `-1` removes the icon.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum IconSize {
+    Large,
+    Medium,
+    Small,
+    Disabled,
+}
+
+/// Colors of minimap icons and beam effects.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum EffectColor {
+    Red,
+    Green,
+    Blue,
+    Brown,
+    White,
+    Yellow,
+    Cyan,
+    Grey,
+    Orange,
+    Pink,
+    Purple,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum IconShape {
+    Circle,
+    Diamond,
+    Hexagon,
+    Square,
+    Star,
+    Triangle,
+    Cross,
+    Moon,
+    Raindrop,
+    Kite,
+    Pentagon,
+    UpsideDownHouse,
+}
+
+/// Volume of an alert sound when none is given.
+pub const DEFAULT_VOLUME: u16 = 50;
+pub const MAX_VOLUME: u16 = 300;
+
+/// One of the game's numbered alert sounds, 1 to 16, or one of its named
+/// currency sounds.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum SoundId {
+    Number(u8),
+    ShAlchemy,
+    ShBlessed,
+    ShChaos,
+    ShDivine,
+    ShExalted,
+    ShFusing,
+    ShGeneral,
+    ShMirror,
+    ShRegal,
+    ShVaal,
+}
+
+/// `PlayAlertSound` or, if `positional`, `PlayAlertSoundPositional`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct AlertSound {
+    pub id: SoundId,
+    pub volume: u16,
+    pub positional: bool,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum Condition {
+    AreaLevel(Op, u32),
+    ItemLevel(Op, u32),
+    DropLevel(Op, u32),
+    Quality(Op, u32),
+    Rarity(Op, Vec<Rarity>),
+    Class {
+        exact: bool,
+        names: Vec<String>,
+    },
+    BaseType {
+        exact: bool,
+        names: Vec<String>,
+    },
+    Prophecy {
+        exact: bool,
+        names: Vec<String>,
+    },
+    LinkedSockets(Op, u32),
+    SocketGroup(Op, Vec<SocketSpec>),
+    Sockets(Op, Vec<SocketSpec>),
+    Height(Op, u32),
+    Width(Op, u32),
+    /// `count` is the number before the names in
+    /// `HasExplicitMod >= 2 "of Haast" "of Tzteosh"`, compared with the
+    /// operator to how many of the item's mods match.
+    HasExplicitMod {
+        exact: bool,
+        count: Option<u32>,
+        names: Vec<String>,
+    },
+    AnyEnchantment(bool),
+    HasEnchantment {
+        exact: bool,
+        count: Option<u32>,
+        names: Vec<String>,
+    },
+    StackSize(Op, u32),
+    GemLevel(Op, u32),
+    Identified(bool),
+    Corrupted(bool),
+    CorruptedMods(Op, u32),
+    Mirrored(bool),
+    ElderItem(bool),
+    ShaperItem(bool),
+    HasInfluence(Vec<Influence>),
+    FracturedItem(bool),
+    SynthesisedItem(bool),
+    ShapedMap(bool),
+    MapTier(Op, u32),
+    BlightedMap(bool),
+    ElderMap(bool),
+    Replica(bool),
+    GemQualityType(Vec<String>),
+    AlternateQuality(bool),
+    EnchantmentPassiveNode {
+        exact: bool,
+        names: Vec<String>,
+    },
+    EnchantmentPassiveNum(Op, u32),
+    HasSearingExarchImplicit(Op, u32),
+    HasEaterOfWorldsImplicit(Op, u32),
+    ArchnemesisMod {
+        exact: bool,
+        names: Vec<String>,
+    },
+    UberBlightedMap(bool),
+    BaseDefencePercentile(Op, u32),
+    Scourged(bool),
+    TransfiguredGem(bool),
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum Action {
+    SetBorderColor(Rgba),
+    SetTextColor(Rgba),
+    SetBackgroundColor(Rgba),
+    SetFontSize(u32),
+    PlayAlertSound(AlertSound),
+    /// `DisableDropSound` on its own is `DisableDropSound(true)`.
+    DisableDropSound(bool),
+    EnableDropSound(bool),
+    CustomAlertSound {
+        path: String,
+        volume: Option<u16>,
+    },
+    /// `color` and `shape` are `None` only for `IconSize::Disabled`.
+    MinimapIcon {
+        size: IconSize,
+        color: Option<EffectColor>,
+        shape: Option<IconShape>,
+    },
+    /// `color` is `None` for `PlayEffect None`, which removes the beam.
+    PlayEffect {
+        color: Option<EffectColor>,
+        temp: bool,
+    },
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum Statement {
+    Condition(Condition),
+    Action(Action),
+}
+
+/// Why a keyword's values could not be turned into a typed statement.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ValueError {
+    pub message: String,
+    pub span: Option<Range<usize>>,
+}
+
+impl ValueError {
+    fn new(message: String, span: Option<Range<usize>>) -> Self {
+        ValueError { message, span }
+    }
+}
+
+impl EffectColor {
+    /// How the game draws this color, from `color::PALETTE`.
+    pub fn rgba(&self) -> Rgba {
+        Rgba::named(&format!("{:?}", self)).unwrap()
+    }
+}
+
+/// A diagnostic for every keyword whose values do not make a valid
+/// statement.
+pub fn validate(source: &str, blocks: &[FilterBlock]) -> Vec<Diagnostic> {
+    blocks
+        .iter()
+        .flat_map(|b| b.keywords.iter())
+        .filter_map(|k| k.statement().err())
+        .map(|e| {
+            let span = e.span.unwrap_or_default();
+            Diagnostic::new(source, Severity::Error, e.message, span)
+        })
+        .collect()
+}
+
+impl FilterBlock<'_> {
+    /// Every keyword of the block that converts to a valid condition.
+    pub fn conditions(&self) -> Vec<Condition> {
+        self.keywords
+            .iter()
+            .filter_map(|k| match k.statement() {
+                Ok(Statement::Condition(c)) => Some(c),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Every keyword of the block that converts to a valid action.
+    pub fn actions(&self) -> Vec<Action> {
+        self.keywords
+            .iter()
+            .filter_map(|k| match k.statement() {
+                Ok(Statement::Action(a)) => Some(a),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+impl TokenAndSpan<'_> {
+    pub fn statement(&self) -> Result<Statement, ValueError> {
+        let c = Statement::Condition;
+        let a = Statement::Action;
+        Ok(match self.token {
+            Token::AreaLevel => c(Condition::AreaLevel(self.op(), self.number(0)?)),
+            Token::ItemLevel => c(Condition::ItemLevel(self.op(), self.number(0)?)),
+            Token::DropLevel => c(Condition::DropLevel(self.op(), self.number(0)?)),
+            Token::Quality => c(Condition::Quality(self.op(), self.number(0)?)),
+            Token::Rarity => c(Condition::Rarity(self.op(), self.each(parse_rarity)?)),
+            Token::Class => c(Condition::Class {
+                exact: self.exact(),
+                names: self.names()?,
+            }),
+            Token::BaseType => c(Condition::BaseType {
+                exact: self.exact(),
+                names: self.names()?,
+            }),
+            Token::Prophecy => c(Condition::Prophecy {
+                exact: self.exact(),
+                names: self.names()?,
+            }),
+            Token::LinkedSockets => c(Condition::LinkedSockets(self.op(), self.number(0)?)),
+            Token::SocketGroup => c(Condition::SocketGroup(self.op(), self.sockets()?)),
+            Token::Sockets => c(Condition::Sockets(self.op(), self.sockets()?)),
+            Token::Height => c(Condition::Height(self.op(), self.number(0)?)),
+            Token::Width => c(Condition::Width(self.op(), self.number(0)?)),
+            Token::HasExplicitMod => {
+                let (count, names) = self.counted_names()?;
+                c(Condition::HasExplicitMod {
+                    exact: self.exact(),
+                    count,
+                    names,
+                })
+            }
+            Token::AnyEnchantment => c(Condition::AnyEnchantment(self.boolean()?)),
+            Token::HasEnchantment => {
+                let (count, names) = self.counted_names()?;
+                c(Condition::HasEnchantment {
+                    exact: self.exact(),
+                    count,
+                    names,
+                })
+            }
+            Token::StackSize => c(Condition::StackSize(self.op(), self.number(0)?)),
+            Token::GemLevel => c(Condition::GemLevel(self.op(), self.number(0)?)),
+            Token::Identified => c(Condition::Identified(self.boolean()?)),
+            Token::Corrupted => c(Condition::Corrupted(self.boolean()?)),
+            Token::CorruptedMods => c(Condition::CorruptedMods(self.op(), self.number(0)?)),
+            Token::Mirrored => c(Condition::Mirrored(self.boolean()?)),
+            Token::ElderItem => c(Condition::ElderItem(self.boolean()?)),
+            Token::ShaperItem => c(Condition::ShaperItem(self.boolean()?)),
+            Token::HasInfluence => c(Condition::HasInfluence(self.each(parse_influence)?)),
+            Token::FracturedItem => c(Condition::FracturedItem(self.boolean()?)),
+            Token::SynthesisedItem => c(Condition::SynthesisedItem(self.boolean()?)),
+            Token::ShapedMap => c(Condition::ShapedMap(self.boolean()?)),
+            Token::MapTier => c(Condition::MapTier(self.op(), self.number(0)?)),
+            Token::BlightedMap => c(Condition::BlightedMap(self.boolean()?)),
+            Token::ElderMap => c(Condition::ElderMap(self.boolean()?)),
+            Token::Replica => c(Condition::Replica(self.boolean()?)),
+            Token::GemQualityType => c(Condition::GemQualityType(self.names()?)),
+            Token::AlternateQuality => c(Condition::AlternateQuality(self.boolean()?)),
+            Token::EnchantmentPassiveNode => c(Condition::EnchantmentPassiveNode {
+                exact: self.exact(),
+                names: self.names()?,
+            }),
+            Token::EnchantmentPassiveNum => {
+                c(Condition::EnchantmentPassiveNum(self.op(), self.number(0)?))
+            }
+            Token::HasSearingExarchImplicit => c(Condition::HasSearingExarchImplicit(
+                self.op(),
+                self.number(0)?,
+            )),
+            Token::HasEaterOfWorldsImplicit => c(Condition::HasEaterOfWorldsImplicit(
+                self.op(),
+                self.number(0)?,
+            )),
+            Token::ArchnemesisMod => c(Condition::ArchnemesisMod {
+                exact: self.exact(),
+                names: self.names()?,
+            }),
+            Token::UberBlightedMap => c(Condition::UberBlightedMap(self.boolean()?)),
+            Token::BaseDefencePercentile => {
+                c(Condition::BaseDefencePercentile(self.op(), self.number(0)?))
+            }
+            Token::Scourged => c(Condition::Scourged(self.boolean()?)),
+            Token::TransfiguredGem => c(Condition::TransfiguredGem(self.boolean()?)),
+
+            Token::SetBorderColor => a(Action::SetBorderColor(self.color()?)),
+            Token::SetTextColor => a(Action::SetTextColor(self.color()?)),
+            Token::SetBackgroundColor => a(Action::SetBackgroundColor(self.color()?)),
+            Token::SetFontSize => a(Action::SetFontSize(self.number(0)?)),
+            Token::PlayAlertSound => a(self.alert_sound(false)?),
+            Token::PlayAlertSoundPositional => a(self.alert_sound(true)?),
+            Token::DisableDropSound => a(Action::DisableDropSound(self.flag()?)),
+            Token::EnableDropSound => a(Action::EnableDropSound(self.flag()?)),
+            Token::CustomAlertSound => a(Action::CustomAlertSound {
+                path: self.text(0)?,
+                volume: self.volume(1)?,
+            }),
+            Token::MinimapIcon => a(self.minimap_icon()?),
+            Token::PlayEffect => a(self.play_effect()?),
+            _ => {
+                return Err(ValueError::new(
+                    format!("`{:?}` is not a condition or an action", self.token),
+                    self.span.clone(),
+                ))
+            }
+        })
+    }
+
+    /// The operator of the keyword, `Op::Eq` when there is none.
+    pub fn op(&self) -> Op {
+        match self.operator.as_ref().map(|o| &o.token) {
+            Some(Token::Less) => Op::Lt,
+            Some(Token::LessEqual) => Op::Le,
+            Some(Token::Greater) => Op::Gt,
+            Some(Token::GreaterEqual) => Op::Ge,
+            Some(Token::ExactEqual) => Op::Exact,
+            Some(Token::Not) | Some(Token::NotEqual) => Op::NotEq,
+            _ => Op::Eq,
+        }
+    }
+
+    fn exact(&self) -> bool {
+        self.op() == Op::Exact
+    }
+
+    fn get(&self, index: usize) -> Result<&ValueAndSpan<'_>, ValueError> {
+        self.value.get(index).ok_or_else(|| {
+            ValueError::new(
+                format!("`{:?}` is missing value {}", self.token, index + 1),
+                self.span.clone(),
+            )
+        })
+    }
+
+    fn text(&self, index: usize) -> Result<String, ValueError> {
+        self.get(index).map(|v| unquote(&v.value).to_string())
+    }
+
+    fn number(&self, index: usize) -> Result<u32, ValueError> {
+        let v = self.get(index)?;
+        v.value.parse().map_err(|_| {
+            ValueError::new(
+                format!("`{:?}` expects a number, found `{}`", self.token, v.value),
+                v.span.clone(),
+            )
+        })
+    }
+
+    fn optional_number(&self, index: usize) -> Result<Option<u32>, ValueError> {
+        if self.value.len() > index {
+            self.number(index).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn boolean(&self) -> Result<bool, ValueError> {
+        let v = self.get(0)?;
+        match v.value.as_ref() {
+            "True" | "true" => Ok(true),
+            "False" | "false" => Ok(false),
+            other => Err(ValueError::new(
+                format!(
+                    "`{:?}` expects True or False, found `{}`",
+                    self.token, other
+                ),
+                v.span.clone(),
+            )),
+        }
+    }
+
+    /// A `True`/`False` value that defaults to `True` when left out.
+    fn flag(&self) -> Result<bool, ValueError> {
+        if self.value.is_empty() {
+            Ok(true)
+        } else {
+            self.boolean()
+        }
+    }
+
+    fn names(&self) -> Result<Vec<String>, ValueError> {
+        self.get(0)?;
+        Ok(self
+            .value
+            .iter()
+            .map(|v| unquote(&v.value).to_string())
+            .collect())
+    }
+
+    /// Names that may follow a count, as in `>= 2 "of Haast" "of Tzteosh"`.
+    /// A number is only a count if names follow it.
+    fn counted_names(&self) -> Result<(Option<u32>, Vec<String>), ValueError> {
+        let mut names = self.names()?;
+        if names.len() > 1 && matches!(self.value[0].token, Token::Numbers(_)) {
+            let count = self.number(0)?;
+            names.remove(0);
+            return Ok((Some(count), names));
+        }
+        Ok((None, names))
+    }
+
+    fn each<T>(&self, f: fn(&str) -> Option<T>) -> Result<Vec<T>, ValueError> {
+        self.get(0)?;
+        self.value
+            .iter()
+            .map(|v| {
+                f(unquote(&v.value)).ok_or_else(|| {
+                    ValueError::new(
+                        format!("invalid `{:?}` value `{}`", self.token, v.value),
+                        v.span.clone(),
+                    )
+                })
+            })
+            .collect()
+    }
+
+    /// Socket specs like `5RRG` lex as a number followed by text, so
+    /// values that touch each other are joined before parsing.
+    fn sockets(&self) -> Result<Vec<SocketSpec>, ValueError> {
+        self.get(0)?;
+        let mut joined: Vec<(String, Option<Range<usize>>)> = vec![];
+        for v in self.value.iter() {
+            match joined.last_mut() {
+                Some((text, Some(span))) if v.span.as_ref().map(|s| s.start) == Some(span.end) => {
+                    text.push_str(&v.value);
+                    span.end = v.span.as_ref().unwrap().end;
+                }
+                _ => joined.push((v.value.to_string(), v.span.clone())),
+            }
+        }
+        joined
+            .into_iter()
+            .map(|(text, span)| {
+                parse_sockets(unquote(&text)).ok_or_else(|| {
+                    ValueError::new(format!("invalid `{:?}` value `{}`", self.token, text), span)
+                })
+            })
+            .collect()
+    }
+
+    /// Reads the value at `index` with `f`, naming the keyword in the error.
+    fn typed<T>(&self, index: usize, f: fn(&str) -> Option<T>) -> Result<T, ValueError> {
+        let v = self.get(index)?;
+        f(unquote(&v.value)).ok_or_else(|| {
+            ValueError::new(
+                format!("invalid `{:?}` value `{}`", self.token, v.value),
+                v.span.clone(),
+            )
+        })
+    }
+
+    fn too_many(&self, count: usize, message: &str) -> Result<(), ValueError> {
+        match self.value.get(count) {
+            Some(v) => Err(ValueError::new(message.to_string(), v.span.clone())),
+            None => Ok(()),
+        }
+    }
+
+    fn minimap_icon(&self) -> Result<Action, ValueError> {
+        let size = self.typed(0, parse_icon_size)?;
+        if size == IconSize::Disabled {
+            self.too_many(1, "`MinimapIcon -1` takes no color or shape")?;
+            return Ok(Action::MinimapIcon {
+                size,
+                color: None,
+                shape: None,
+            });
+        }
+        let color = self.typed(1, parse_effect_color)?;
+        let shape = self.typed(2, parse_icon_shape)?;
+        self.too_many(3, "`MinimapIcon` takes a size, a color and a shape")?;
+        Ok(Action::MinimapIcon {
+            size,
+            color: Some(color),
+            shape: Some(shape),
+        })
+    }
+
+    fn play_effect(&self) -> Result<Action, ValueError> {
+        let color = match self.get(0)?.value.as_ref() {
+            "None" => None,
+            _ => Some(self.typed(0, parse_effect_color)?),
+        };
+        let temp = match self.value.get(1) {
+            Some(v) if v.value == "Temp" && color.is_none() => {
+                return Err(ValueError::new(
+                    "`PlayEffect None` can not be `Temp`".to_string(),
+                    v.span.clone(),
+                ))
+            }
+            Some(v) if v.value == "Temp" => true,
+            Some(v) => {
+                return Err(ValueError::new(
+                    format!(
+                        "expected `Temp` after the `PlayEffect` color, found `{}`",
+                        v.value
+                    ),
+                    v.span.clone(),
+                ))
+            }
+            None => false,
+        };
+        self.too_many(2, "`PlayEffect` takes a color and an optional `Temp`")?;
+        Ok(Action::PlayEffect { color, temp })
+    }
+
+    fn volume(&self, index: usize) -> Result<Option<u16>, ValueError> {
+        let volume = match self.optional_number(index)? {
+            Some(volume) => volume,
+            None => return Ok(None),
+        };
+        if volume > MAX_VOLUME as u32 {
+            return Err(ValueError::new(
+                format!("volume `{}` is not in 0-{}", volume, MAX_VOLUME),
+                self.value[index].span.clone(),
+            ));
+        }
+        Ok(Some(volume as u16))
+    }
+
+    fn alert_sound(&self, positional: bool) -> Result<Action, ValueError> {
+        let v = self.get(0)?;
+        let id = parse_sound_id(unquote(&v.value)).ok_or_else(|| {
+            ValueError::new(format!("unknown alert sound `{}`", v.value), v.span.clone())
+        })?;
+        Ok(Action::PlayAlertSound(AlertSound {
+            id,
+            volume: self.volume(1)?.unwrap_or(DEFAULT_VOLUME),
+            positional,
+        }))
+    }
+
+    fn color(&self) -> Result<Rgba, ValueError> {
+        let values: Vec<&str> = self.value.iter().map(|v| v.value.as_ref()).collect();
+        Rgba::from_components(&values).map_err(|e| {
+            let span = match e.index {
+                Some(index) => self.value[index].span.clone(),
+                None => self.span.clone(),
+            };
+            ValueError::new(e.message, span)
+        })
+    }
+}
+
+fn unquote(s: &str) -> &str {
+    s.trim_matches('"')
+}
+
+fn parse_rarity(s: &str) -> Option<Rarity> {
+    match s {
+        "Normal" => Some(Rarity::Normal),
+        "Magic" => Some(Rarity::Magic),
+        "Rare" => Some(Rarity::Rare),
+        "Unique" => Some(Rarity::Unique),
+        _ => None,
+    }
+}
+
+fn parse_influence(s: &str) -> Option<Influence> {
+    match s {
+        "Shaper" => Some(Influence::Shaper),
+        "Elder" => Some(Influence::Elder),
+        "Crusader" => Some(Influence::Crusader),
+        "Hunter" => Some(Influence::Hunter),
+        "Redeemer" => Some(Influence::Redeemer),
+        "Warlord" => Some(Influence::Warlord),
+        "None" => Some(Influence::None),
+        _ => None,
+    }
+}
+
+fn parse_sound_id(s: &str) -> Option<SoundId> {
+    match s {
+        "ShAlchemy" => Some(SoundId::ShAlchemy),
+        "ShBlessed" => Some(SoundId::ShBlessed),
+        "ShChaos" => Some(SoundId::ShChaos),
+        "ShDivine" => Some(SoundId::ShDivine),
+        "ShExalted" => Some(SoundId::ShExalted),
+        "ShFusing" => Some(SoundId::ShFusing),
+        "ShGeneral" => Some(SoundId::ShGeneral),
+        "ShMirror" => Some(SoundId::ShMirror),
+        "ShRegal" => Some(SoundId::ShRegal),
+        "ShVaal" => Some(SoundId::ShVaal),
+        _ => s
+            .parse()
+            .ok()
+            .filter(|n| (1..=16).contains(n))
+            .map(SoundId::Number),
+    }
+}
+
+fn parse_icon_size(s: &str) -> Option<IconSize> {
+    match s {
+        "0" => Some(IconSize::Large),
+        "1" => Some(IconSize::Medium),
+        "2" => Some(IconSize::Small),
+        "-1" => Some(IconSize::Disabled),
+        _ => None,
+    }
+}
+
+fn parse_effect_color(s: &str) -> Option<EffectColor> {
+    match s {
+        "Red" => Some(EffectColor::Red),
+        "Green" => Some(EffectColor::Green),
+        "Blue" => Some(EffectColor::Blue),
+        "Brown" => Some(EffectColor::Brown),
+        "White" => Some(EffectColor::White),
+        "Yellow" => Some(EffectColor::Yellow),
+        "Cyan" => Some(EffectColor::Cyan),
+        "Grey" => Some(EffectColor::Grey),
+        "Orange" => Some(EffectColor::Orange),
+        "Pink" => Some(EffectColor::Pink),
+        "Purple" => Some(EffectColor::Purple),
+        _ => None,
+    }
+}
+
+fn parse_icon_shape(s: &str) -> Option<IconShape> {
+    match s {
+        "Circle" => Some(IconShape::Circle),
+        "Diamond" => Some(IconShape::Diamond),
+        "Hexagon" => Some(IconShape::Hexagon),
+        "Square" => Some(IconShape::Square),
+        "Star" => Some(IconShape::Star),
+        "Triangle" => Some(IconShape::Triangle),
+        "Cross" => Some(IconShape::Cross),
+        "Moon" => Some(IconShape::Moon),
+        "Raindrop" => Some(IconShape::Raindrop),
+        "Kite" => Some(IconShape::Kite),
+        "Pentagon" => Some(IconShape::Pentagon),
+        "UpsideDownHouse" => Some(IconShape::UpsideDownHouse),
+        _ => None,
+    }
+}
+
+fn parse_sockets(s: &str) -> Option<SocketSpec> {
+    let digits = s.chars().take_while(|c| c.is_ascii_digit()).count();
+    let mut spec = SocketSpec {
+        count: if digits > 0 {
+            s[..digits].parse().ok()
+        } else {
+            None
+        },
+        ..Default::default()
+    };
+    for c in s[digits..].chars() {
+        match c {
+            'R' => spec.red += 1,
+            'G' => spec.green += 1,
+            'B' => spec.blue += 1,
+            'W' => spec.white += 1,
+            'A' => spec.abyss += 1,
+            'D' => spec.delve += 1,
+            _ => return None,
+        }
+    }
+    if s.is_empty() {
+        None
+    } else {
+        Some(spec)
+    }
+}
@@ -0,0 +1,214 @@
+use std::fmt;
+
+/// Alpha used when a color is given with three components.
+pub const DEFAULT_ALPHA: u8 = 255;
+
+/// A `SetTextColor`, `SetBorderColor` or `SetBackgroundColor` color.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Rgba {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
+/// Hue in degrees, saturation and lightness in 0-1.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Hsl {
+    pub h: f64,
+    pub s: f64,
+    pub l: f64,
+}
+
+/// A color in the OKLab space, where distances match perceived differences.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Oklab {
+    pub l: f64,
+    pub a: f64,
+    pub b: f64,
+}
+
+/// Why color components could not be read. `index` is the component at
+/// fault, if it is a single one.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ColorError {
+    pub message: String,
+    pub index: Option<usize>,
+}
+
+/// The colors `MinimapIcon` and `PlayEffect` take, as the game shows them.
+pub static PALETTE: &[(&str, Rgba)] = &[
+    ("Red", Rgba::new(250, 50, 50)),
+    ("Green", Rgba::new(50, 200, 50)),
+    ("Blue", Rgba::new(50, 100, 250)),
+    ("Brown", Rgba::new(160, 100, 50)),
+    ("White", Rgba::new(255, 255, 255)),
+    ("Yellow", Rgba::new(250, 220, 50)),
+    ("Cyan", Rgba::new(50, 220, 250)),
+    ("Grey", Rgba::new(150, 150, 150)),
+    ("Orange", Rgba::new(250, 140, 30)),
+    ("Pink", Rgba::new(250, 120, 200)),
+    ("Purple", Rgba::new(160, 60, 220)),
+];
+
+impl Rgba {
+    pub const fn new(r: u8, g: u8, b: u8) -> Self {
+        Rgba {
+            r,
+            g,
+            b,
+            a: DEFAULT_ALPHA,
+        }
+    }
+
+    pub const fn with_alpha(self, a: u8) -> Self {
+        Rgba { a, ..self }
+    }
+
+    /// Reads the 3 or 4 values of a color keyword, like `255 190 0` or
+    /// `0 240 190 240`.
+    pub fn from_components<S: AsRef<str>>(values: &[S]) -> Result<Self, ColorError> {
+        if values.len() < 3 || values.len() > 4 {
+            return Err(ColorError {
+                message: format!("expected 3 or 4 color components, found {}", values.len()),
+                index: None,
+            });
+        }
+        let mut components = [DEFAULT_ALPHA; 4];
+        for (index, value) in values.iter().enumerate() {
+            let value = value.as_ref();
+            components[index] = match value.parse::<i64>() {
+                Ok(n) if (0..=255).contains(&n) => n as u8,
+                Ok(_) => {
+                    return Err(ColorError {
+                        message: format!("color component `{}` is not in 0-255", value),
+                        index: Some(index),
+                    })
+                }
+                Err(_) => {
+                    return Err(ColorError {
+                        message: format!("color component `{}` is not a number", value),
+                        index: Some(index),
+                    })
+                }
+            };
+        }
+        let [r, g, b, a] = components;
+        Ok(Rgba { r, g, b, a })
+    }
+
+    /// Looks a color up in `PALETTE`, ignoring case.
+    pub fn named(name: &str) -> Option<Self> {
+        PALETTE
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, c)| *c)
+    }
+
+    /// `#rrggbb`, or `#rrggbbaa` if the color is not opaque.
+    pub fn to_hex(&self) -> String {
+        let hex = format!("#{:02x}{:02x}{:02x}", self.r, self.g, self.b);
+        if self.a == DEFAULT_ALPHA {
+            hex
+        } else {
+            format!("{}{:02x}", hex, self.a)
+        }
+    }
+
+    /// Reads `#rgb`, `#rrggbb` or `#rrggbbaa`, with or without the `#`.
+    pub fn from_hex(hex: &str) -> Option<Self> {
+        let hex = hex.strip_prefix('#').unwrap_or(hex);
+        if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+        let byte = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+        match hex.len() {
+            3 => {
+                let nibble = |i: usize| u8::from_str_radix(&hex[i..i + 1], 16).ok().map(|n| n * 17);
+                Some(Rgba::new(nibble(0)?, nibble(1)?, nibble(2)?))
+            }
+            6 => Some(Rgba::new(byte(0)?, byte(2)?, byte(4)?)),
+            8 => Some(Rgba::new(byte(0)?, byte(2)?, byte(4)?).with_alpha(byte(6)?)),
+            _ => None,
+        }
+    }
+
+    pub fn to_hsl(&self) -> Hsl {
+        let [r, g, b] = [self.r, self.g, self.b].map(|c| c as f64 / 255.0);
+        let max = r.max(g).max(b);
+        let min = r.min(g).min(b);
+        let l = (max + min) / 2.0;
+        let d = max - min;
+        if d == 0.0 {
+            return Hsl { h: 0.0, s: 0.0, l };
+        }
+        let s = d / (1.0 - (2.0 * l - 1.0).abs());
+        let h = if max == r {
+            60.0 * ((g - b) / d).rem_euclid(6.0)
+        } else if max == g {
+            60.0 * ((b - r) / d + 2.0)
+        } else {
+            60.0 * ((r - g) / d + 4.0)
+        };
+        Hsl { h, s, l }
+    }
+
+    pub fn to_oklab(&self) -> Oklab {
+        let [r, g, b] = [self.r, self.g, self.b].map(|c| {
+            let c = c as f64 / 255.0;
+            if c <= 0.04045 {
+                c / 12.92
+            } else {
+                ((c + 0.055) / 1.055).powf(2.4)
+            }
+        });
+        let l = (0.4122214708 * r + 0.5363325363 * g + 0.0514459929 * b).cbrt();
+        let m = (0.2119034982 * r + 0.6806995451 * g + 0.1073969566 * b).cbrt();
+        let s = (0.0883024619 * r + 0.2817188376 * g + 0.6299787005 * b).cbrt();
+        Oklab {
+            l: 0.2104542553 * l + 0.7936177850 * m - 0.0040720468 * s,
+            a: 1.9779984951 * l - 2.4285922050 * m + 0.4505937099 * s,
+            b: 0.0259040371 * l + 0.7827717662 * m - 0.8086757660 * s,
+        }
+    }
+}
+
+impl Hsl {
+    pub fn to_rgba(&self) -> Rgba {
+        let c = (1.0 - (2.0 * self.l - 1.0).abs()) * self.s;
+        let h = self.h.rem_euclid(360.0) / 60.0;
+        let x = c * (1.0 - (h % 2.0 - 1.0).abs());
+        let (r, g, b) = match h as u32 {
+            0 => (c, x, 0.0),
+            1 => (x, c, 0.0),
+            2 => (0.0, c, x),
+            3 => (0.0, x, c),
+            4 => (x, 0.0, c),
+            _ => (c, 0.0, x),
+        };
+        let m = self.l - c / 2.0;
+        let byte = |v: f64| ((v + m) * 255.0).round().clamp(0.0, 255.0) as u8;
+        Rgba::new(byte(r), byte(g), byte(b))
+    }
+}
+
+impl Oklab {
+    /// Euclidean distance, a good measure of how different two colors look.
+    pub fn distance(&self, other: &Oklab) -> f64 {
+        ((self.l - other.l).powi(2) + (self.a - other.a).powi(2) + (self.b - other.b).powi(2))
+            .sqrt()
+    }
+}
+
+/// Written as the values of a color keyword, leaving out an opaque alpha.
+impl fmt::Display for Rgba {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {} {}", self.r, self.g, self.b)?;
+        if self.a != DEFAULT_ALPHA {
+            write!(f, " {}", self.a)?;
+        }
+        Ok(())
+    }
+}
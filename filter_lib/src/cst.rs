@@ -0,0 +1,238 @@
+use crate::mode_parsing::{ParseOptions, ParseOutput, Parser, Token};
+use logos::Logos;
+use std::fmt;
+use std::ops::Range;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum TriviaKind {
+    Whitespace,
+    Comment,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Trivia {
+    pub kind: TriviaKind,
+    pub text: String,
+    pub span: Range<usize>,
+}
+
+/// A token together with the whitespace that precedes it on its line.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SyntaxToken {
+    pub leading_trivia: Vec<Trivia>,
+    pub token: Token<'static>,
+    pub text: String,
+    pub span: Range<usize>,
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct CstLine {
+    pub tokens: Vec<SyntaxToken>,
+    /// Whitespace and comment after the last token, or the whole line if it
+    /// has no tokens.
+    pub trailing_trivia: Vec<Trivia>,
+    /// `"\n"` or `"\r\n"`, or empty for a last line without a newline.
+    pub line_end: String,
+}
+
+/// A `Show`/`Hide` header line and every line up to the next header. The
+/// first block of a file holds whatever comes before the first header, and
+/// an `Import` line starts a block of its own, as it does when parsing.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct CstBlock {
+    pub lines: Vec<CstLine>,
+}
+
+/// Lossless syntax tree: printing it gives back the source it was built from.
+/// Spans refer to the original source and are not updated by edits.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Cst {
+    pub blocks: Vec<CstBlock>,
+}
+
+impl CstLine {
+    pub fn keyword(&self) -> Option<&SyntaxToken> {
+        self.tokens.first()
+    }
+
+    pub fn comment(&self) -> Option<&Trivia> {
+        self.trailing_trivia
+            .iter()
+            .find(|t| t.kind == TriviaKind::Comment)
+    }
+
+    pub fn is_header(&self) -> bool {
+        matches!(
+            self.keyword().map(|t| &t.token),
+            Some(Token::Show) | Some(Token::Hide)
+        )
+    }
+
+    pub fn is_import(&self) -> bool {
+        self.keyword().map(|t| &t.token) == Some(&Token::Import)
+    }
+}
+
+impl CstBlock {
+    pub fn header(&self) -> Option<&CstLine> {
+        self.lines.first().filter(|l| l.is_header())
+    }
+}
+
+impl Cst {
+    pub fn lines(&self) -> impl Iterator<Item = &CstLine> {
+        self.blocks.iter().flat_map(|b| b.lines.iter())
+    }
+
+    /// Typed view of the tree. Spans in the output refer to the current text
+    /// of the tree, so they stay correct after edits.
+    pub fn parse(&self) -> ParseOutput<'static> {
+        let source = self.to_string();
+        let mut parser = Parser::new(&source, ParseOptions::default());
+        let mut offset = 0;
+        for line in self.lines() {
+            for token in line.tokens.iter() {
+                offset += token
+                    .leading_trivia
+                    .iter()
+                    .map(|t| t.text.len())
+                    .sum::<usize>();
+                let span = offset..offset + token.text.len();
+                offset = span.end;
+                parser.match_filter(token.token.clone(), span);
+            }
+            for trivia in line.trailing_trivia.iter() {
+                if trivia.kind == TriviaKind::Comment {
+                    parser.match_filter(Token::Hash, offset..offset + trivia.text.len());
+                }
+                offset += trivia.text.len();
+            }
+            let span = offset..offset + line.line_end.len();
+            offset = span.end;
+            parser.match_filter(Token::EndLine, span);
+        }
+        parser.finish().into_owned()
+    }
+}
+
+pub fn parse_cst(source: &str) -> Cst {
+    let mut cst = Cst {
+        blocks: vec![CstBlock::default()],
+    };
+    let mut start = 0;
+    while start < source.len() {
+        let end = source[start..]
+            .find('\n')
+            .map_or(source.len(), |i| start + i + 1);
+        let line = parse_line(source, start..end);
+        if line.is_header() || line.is_import() {
+            cst.blocks.push(CstBlock::default());
+        }
+        cst.blocks.last_mut().unwrap().lines.push(line);
+        start = end;
+    }
+    cst
+}
+
+fn parse_line(source: &str, span: Range<usize>) -> CstLine {
+    let text = &source[span.clone()];
+    let content_len = text
+        .strip_suffix('\n')
+        .map_or(text, |t| t.strip_suffix('\r').unwrap_or(t))
+        .len();
+    let code_len = comment_start(&text[..content_len]).unwrap_or(content_len);
+    let code = &text[..code_len];
+
+    let mut line = CstLine {
+        line_end: text[content_len..].to_string(),
+        ..Default::default()
+    };
+    let mut offset = 0;
+    for (token, token_span) in Token::lexer(code).spanned() {
+        line.tokens.push(SyntaxToken {
+            leading_trivia: whitespace(code, offset..token_span.start, span.start),
+            token: token.into_owned(),
+            text: code[token_span.clone()].to_string(),
+            span: span.start + token_span.start..span.start + token_span.end,
+        });
+        offset = token_span.end;
+    }
+    line.trailing_trivia = whitespace(code, offset..code_len, span.start);
+    if code_len < content_len {
+        line.trailing_trivia.push(Trivia {
+            kind: TriviaKind::Comment,
+            text: text[code_len..content_len].to_string(),
+            span: span.start + code_len..span.start + content_len,
+        });
+    }
+    line
+}
+
+fn whitespace(code: &str, range: Range<usize>, base: usize) -> Vec<Trivia> {
+    if range.is_empty() {
+        return vec![];
+    }
+    vec![Trivia {
+        kind: TriviaKind::Whitespace,
+        text: code[range.clone()].to_string(),
+        span: base + range.start..base + range.end,
+    }]
+}
+
+/// Byte offset of the first `#` that is not inside a quoted string.
+pub fn comment_start(line: &str) -> Option<usize> {
+    let mut quoted = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            '#' if !quoted => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+impl fmt::Display for Trivia {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+impl fmt::Display for SyntaxToken {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for trivia in self.leading_trivia.iter() {
+            write!(f, "{}", trivia)?;
+        }
+        f.write_str(&self.text)
+    }
+}
+
+impl fmt::Display for CstLine {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for token in self.tokens.iter() {
+            write!(f, "{}", token)?;
+        }
+        for trivia in self.trailing_trivia.iter() {
+            write!(f, "{}", trivia)?;
+        }
+        f.write_str(&self.line_end)
+    }
+}
+
+impl fmt::Display for CstBlock {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for line in self.lines.iter() {
+            write!(f, "{}", line)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Cst {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for block in self.blocks.iter() {
+            write!(f, "{}", block)?;
+        }
+        Ok(())
+    }
+}
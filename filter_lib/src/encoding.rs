@@ -0,0 +1,223 @@
+use std::borrow::Cow;
+use std::fmt;
+use std::ops::Range;
+
+const UTF8_BOM: &[u8] = &[0xef, 0xbb, 0xbf];
+const UTF16_LE_BOM: &[u8] = &[0xff, 0xfe];
+const UTF16_BE_BOM: &[u8] = &[0xfe, 0xff];
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Hash)]
+pub enum Encoding {
+    #[default]
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Hash)]
+pub enum LineEnding {
+    #[default]
+    Lf,
+    CrLf,
+}
+
+/// How a filter file was written, so that it can be written back the same
+/// way.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Hash)]
+pub struct TextFormat {
+    pub encoding: Encoding,
+    pub bom: bool,
+    pub line_ending: LineEnding,
+}
+
+/// Why bytes could not be decoded. `offset` is where in them the problem is.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DecodeError {
+    pub message: String,
+    pub offset: usize,
+}
+
+/// The text of a filter file, ready to parse, and how to get back from it to
+/// the bytes of the file.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct DecodedText {
+    /// The text with `\r\n` line endings turned into `\n`.
+    pub text: String,
+    pub format: TextFormat,
+    /// Offsets in `text` of the `\n`s that were `\r\n`.
+    crlf: Vec<usize>,
+}
+
+impl Encoding {
+    /// Bytes per code unit.
+    fn unit(self) -> usize {
+        match self {
+            Encoding::Utf8 => 1,
+            Encoding::Utf16Le | Encoding::Utf16Be => 2,
+        }
+    }
+}
+
+impl TextFormat {
+    fn bom(&self) -> &'static [u8] {
+        match (self.bom, self.encoding) {
+            (false, _) => &[],
+            (true, Encoding::Utf8) => UTF8_BOM,
+            (true, Encoding::Utf16Le) => UTF16_LE_BOM,
+            (true, Encoding::Utf16Be) => UTF16_BE_BOM,
+        }
+    }
+
+    /// The bytes of a file in this format holding `text`, which has `\n`
+    /// line endings.
+    pub fn encode(&self, text: &str) -> Vec<u8> {
+        let text = match self.line_ending {
+            LineEnding::Lf => Cow::Borrowed(text),
+            LineEnding::CrLf => Cow::Owned(text.replace('\n', "\r\n")),
+        };
+        self.encode_text(&text)
+    }
+
+    /// `text` in this encoding, with its line endings as they are.
+    fn encode_text(&self, text: &str) -> Vec<u8> {
+        let mut bytes = self.bom().to_vec();
+        match self.encoding {
+            Encoding::Utf8 => bytes.extend_from_slice(text.as_bytes()),
+            Encoding::Utf16Le => bytes.extend(text.encode_utf16().flat_map(u16::to_le_bytes)),
+            Encoding::Utf16Be => bytes.extend(text.encode_utf16().flat_map(u16::to_be_bytes)),
+        }
+        bytes
+    }
+}
+
+impl DecodedText {
+    /// Where an offset in `text` is in the bytes it was decoded from.
+    pub fn original_offset(&self, offset: usize) -> usize {
+        let before = &self.text[..offset];
+        let units = match self.format.encoding {
+            Encoding::Utf8 => before.len(),
+            Encoding::Utf16Le | Encoding::Utf16Be => before.encode_utf16().count(),
+        };
+        let carriage_returns = self.crlf.partition_point(|&i| i < offset);
+        self.format.bom().len() + (units + carriage_returns) * self.format.encoding.unit()
+    }
+
+    /// A span of `text`, such as one from `parse`, as a span of the bytes it
+    /// was decoded from.
+    pub fn original_span(&self, span: Range<usize>) -> Range<usize> {
+        self.original_offset(span.start)..self.original_offset(span.end)
+    }
+
+    /// The bytes of `text` after changes, in the format it was read in. Each
+    /// line gets the line ending the line at the same position had, so files
+    /// with mixed line endings keep them; lines past the end of the original
+    /// get the ending of most lines.
+    pub fn encode(&self, text: &str) -> Vec<u8> {
+        let original: Vec<bool> = self
+            .text
+            .match_indices('\n')
+            .map(|(i, _)| self.crlf.binary_search(&i).is_ok())
+            .collect();
+        let default = self.format.line_ending == LineEnding::CrLf;
+        let mut restored = String::with_capacity(text.len() + self.crlf.len());
+        for (line, piece) in text.split_inclusive('\n').enumerate() {
+            match piece.strip_suffix('\n') {
+                Some(content) if original.get(line).copied().unwrap_or(default) => {
+                    restored.push_str(content);
+                    restored.push_str("\r\n");
+                }
+                _ => restored.push_str(piece),
+            }
+        }
+        self.format.encode_text(&restored)
+    }
+}
+
+/// Reads a filter file in UTF-8 or UTF-16, with or without a byte order mark.
+/// UTF-16 without one is recognized by its first character being ASCII, as
+/// it is in any filter. The line ending of most lines is kept in `format`,
+/// and the ending of every line for `DecodedText::encode`.
+pub fn decode(bytes: &[u8]) -> Result<DecodedText, DecodeError> {
+    let mut format = detect(bytes);
+    let start = format.bom().len();
+    let body = &bytes[start..];
+    let decoded = match format.encoding {
+        Encoding::Utf8 => {
+            std::str::from_utf8(body)
+                .map(Cow::Borrowed)
+                .map_err(|e| DecodeError {
+                    message: "invalid UTF-8".to_string(),
+                    offset: start + e.valid_up_to(),
+                })?
+        }
+        Encoding::Utf16Le | Encoding::Utf16Be => {
+            Cow::Owned(decode_utf16(body, format.encoding, start)?)
+        }
+    };
+
+    let mut text = String::with_capacity(decoded.len());
+    let mut crlf = vec![];
+    let mut rest = decoded.as_ref();
+    while let Some(i) = rest.find("\r\n") {
+        text.push_str(&rest[..i]);
+        crlf.push(text.len());
+        text.push('\n');
+        rest = &rest[i + 2..];
+    }
+    text.push_str(rest);
+    if crlf.len() * 2 > text.matches('\n').count() {
+        format.line_ending = LineEnding::CrLf;
+    }
+    Ok(DecodedText { text, format, crlf })
+}
+
+fn detect(bytes: &[u8]) -> TextFormat {
+    let (encoding, bom) = if bytes.starts_with(UTF8_BOM) {
+        (Encoding::Utf8, true)
+    } else if bytes.starts_with(UTF16_LE_BOM) {
+        (Encoding::Utf16Le, true)
+    } else if bytes.starts_with(UTF16_BE_BOM) {
+        (Encoding::Utf16Be, true)
+    } else {
+        match bytes {
+            [first, 0, ..] if *first != 0 => (Encoding::Utf16Le, false),
+            [0, second, ..] if *second != 0 => (Encoding::Utf16Be, false),
+            _ => (Encoding::Utf8, false),
+        }
+    };
+    TextFormat {
+        encoding,
+        bom,
+        line_ending: LineEnding::Lf,
+    }
+}
+
+fn decode_utf16(body: &[u8], encoding: Encoding, start: usize) -> Result<String, DecodeError> {
+    if !body.len().is_multiple_of(2) {
+        return Err(DecodeError {
+            message: "odd number of bytes in UTF-16 text".to_string(),
+            offset: start + body.len() - 1,
+        });
+    }
+    let units = body.chunks_exact(2).map(|pair| match encoding {
+        Encoding::Utf16Be => u16::from_be_bytes([pair[0], pair[1]]),
+        _ => u16::from_le_bytes([pair[0], pair[1]]),
+    });
+    let mut text = String::with_capacity(body.len() / 2);
+    let mut offset = start;
+    for c in char::decode_utf16(units) {
+        let c = c.map_err(|_| DecodeError {
+            message: "unpaired UTF-16 surrogate".to_string(),
+            offset,
+        })?;
+        offset += c.len_utf16() * 2;
+        text.push(c);
+    }
+    Ok(text)
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at byte {}", self.message, self.offset)
+    }
+}
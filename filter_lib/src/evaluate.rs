@@ -0,0 +1,53 @@
+use crate::item::Item;
+use crate::mode_parsing::{FilterBlock, Token};
+
+/// What a filter does with an item.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Verdict {
+    /// Whether the item is shown. Items no block matches are shown.
+    pub visible: bool,
+    /// Index of the block that decided, the last one that matched.
+    pub block: Option<usize>,
+    /// Every block that matched, in order. All but the last have `Continue`.
+    pub matched: Vec<usize>,
+}
+
+/// Runs the `Show` and `Hide` blocks top-down the way the game does: the
+/// first block whose conditions all hold decides, unless it ends with
+/// `Continue`, in which case the blocks after it are tried too and the last
+/// one that matches decides.
+pub fn evaluate(blocks: &[FilterBlock], item: &Item) -> Verdict {
+    run(blocks, item, |_, _, _| {})
+}
+
+/// The loop behind `evaluate`, calling `tried` with the index of each block
+/// it tries, whether that block is `Show` and whether it matched.
+pub(crate) fn run(
+    blocks: &[FilterBlock],
+    item: &Item,
+    mut tried: impl FnMut(usize, bool, bool),
+) -> Verdict {
+    let mut verdict = Verdict {
+        visible: true,
+        ..Default::default()
+    };
+    for (index, block) in blocks.iter().enumerate() {
+        let show = match block.block {
+            Some(Token::Show) => true,
+            Some(Token::Hide) => false,
+            _ => continue,
+        };
+        let matched = block.matches(item);
+        tried(index, show, matched);
+        if !matched {
+            continue;
+        }
+        verdict.visible = show;
+        verdict.block = Some(index);
+        verdict.matched.push(index);
+        if !block.continues {
+            break;
+        }
+    }
+    verdict
+}
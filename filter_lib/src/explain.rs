@@ -0,0 +1,171 @@
+use crate::ast::Influence;
+use crate::evaluate::{run, Verdict};
+use crate::item::Item;
+use crate::mode_parsing::{FilterBlock, KeywordType, Token};
+use crate::source_map::LineIndex;
+use std::fmt::Write;
+use std::ops::Range;
+
+/// One condition of a block tried on an item.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ConditionTrace {
+    /// The condition as written, like `ItemLevel >= 86`.
+    pub condition: String,
+    pub span: Option<Range<usize>>,
+    /// What the item has for the condition to look at, written the way the
+    /// filter writes values.
+    pub actual: String,
+    pub passed: bool,
+}
+
+/// A `Show` or `Hide` block tried on an item.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BlockTrace {
+    /// Index of the block in the blocks given to `explain`.
+    pub index: usize,
+    pub bspan: Option<Range<usize>>,
+    /// `Show` rather than `Hide`.
+    pub show: bool,
+    pub conditions: Vec<ConditionTrace>,
+    pub matched: bool,
+}
+
+/// Every block `evaluate` tried on an item, up to the one that decided.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Explanation {
+    pub blocks: Vec<BlockTrace>,
+    pub verdict: Verdict,
+}
+
+/// Like `evaluate`, but records each condition of each block tried, not only
+/// the outcome.
+pub fn explain(blocks: &[FilterBlock], item: &Item) -> Explanation {
+    let mut traces = vec![];
+    let verdict = run(blocks, item, |index, show, matched| {
+        let conditions = blocks[index]
+            .keywords
+            .iter()
+            .filter(|k| matches!(k.token.keyword_type(), Some(KeywordType::Conditions)))
+            .map(|k| ConditionTrace {
+                condition: k.to_string(),
+                span: k.span.clone(),
+                actual: actual(&k.token, item),
+                passed: k.matches(item),
+            })
+            .collect();
+        traces.push(BlockTrace {
+            index,
+            bspan: blocks[index].bspan.clone(),
+            show,
+            conditions,
+            matched,
+        });
+    });
+    Explanation {
+        blocks: traces,
+        verdict,
+    }
+}
+
+impl Explanation {
+    /// A line for each block tried and each of its conditions, with line and
+    /// column numbers in `source`, the text the blocks were parsed from.
+    pub fn report(&self, source: &str) -> String {
+        let lines = LineIndex::new(source);
+        let position = |span: &Option<Range<usize>>| {
+            let location = lines.location(source, span.as_ref().map_or(0, |s| s.start));
+            format!("{}:{}", location.line, location.column)
+        };
+        let mut out = String::new();
+        for trace in self.blocks.iter() {
+            let header = if trace.show { "Show" } else { "Hide" };
+            let outcome = if !trace.matched {
+                "does not match"
+            } else if Some(trace.index) == self.verdict.block {
+                "matches and decides"
+            } else {
+                "matches and continues"
+            };
+            writeln!(out, "{}: `{}` {}", position(&trace.bspan), header, outcome).unwrap();
+            for condition in trace.conditions.iter() {
+                let result = if condition.passed { "pass" } else { "fail" };
+                writeln!(
+                    out,
+                    "\t{}: {} `{}`, item has {}",
+                    position(&condition.span),
+                    result,
+                    condition.condition,
+                    condition.actual
+                )
+                .unwrap();
+            }
+        }
+        let shown = if self.verdict.visible {
+            "shown"
+        } else {
+            "hidden"
+        };
+        match self.verdict.block {
+            Some(_) => writeln!(out, "item is {}", shown).unwrap(),
+            None => writeln!(out, "no block matches, item is {}", shown).unwrap(),
+        }
+        out
+    }
+}
+
+/// The item's value for a condition keyword.
+fn actual(token: &Token, item: &Item) -> String {
+    let number = |n: u32| n.to_string();
+    let boolean = |b: bool| if b { "True" } else { "False" }.to_string();
+    let names = |names: &[String]| {
+        if names.is_empty() {
+            "none".to_string()
+        } else {
+            let quoted: Vec<_> = names.iter().map(|n| format!("\"{}\"", n)).collect();
+            quoted.join(" ")
+        }
+    };
+    match token {
+        Token::AreaLevel => number(item.area_level),
+        Token::ItemLevel => number(item.item_level),
+        Token::DropLevel => number(item.drop_level),
+        Token::Quality => number(item.quality),
+        Token::Rarity => format!("{:?}", item.rarity),
+        Token::Class => names(std::slice::from_ref(&item.class)),
+        Token::BaseType => names(std::slice::from_ref(&item.base_type)),
+        Token::LinkedSockets => number(item.links()),
+        Token::Sockets | Token::SocketGroup if item.sockets.is_empty() => "no sockets".to_string(),
+        Token::Sockets | Token::SocketGroup => item.sockets.clone(),
+        Token::Height => number(item.height),
+        Token::Width => number(item.width),
+        Token::HasExplicitMod => names(&item.explicit_mods),
+        Token::AnyEnchantment => boolean(!item.enchantments.is_empty()),
+        Token::HasEnchantment => names(&item.enchantments),
+        Token::StackSize => number(item.stack_size),
+        Token::GemLevel => number(item.gem_level),
+        Token::Identified => boolean(item.identified),
+        Token::Corrupted => boolean(item.corrupted),
+        Token::Mirrored => boolean(item.mirrored),
+        Token::ElderItem => boolean(item.influences.contains(&Influence::Elder)),
+        Token::ShaperItem => boolean(item.influences.contains(&Influence::Shaper)),
+        Token::HasInfluence if item.influences.is_empty() => "None".to_string(),
+        Token::HasInfluence => {
+            let influences: Vec<_> = item.influences.iter().map(|i| format!("{:?}", i)).collect();
+            influences.join(" ")
+        }
+        Token::FracturedItem => boolean(item.fractured),
+        Token::SynthesisedItem => boolean(item.synthesised),
+        Token::MapTier => number(item.map_tier),
+        Token::Replica => boolean(item.replica),
+        Token::Prophecy
+        | Token::GemQualityType
+        | Token::EnchantmentPassiveNode
+        | Token::ArchnemesisMod => names(&[]),
+        Token::CorruptedMods
+        | Token::EnchantmentPassiveNum
+        | Token::HasSearingExarchImplicit
+        | Token::HasEaterOfWorldsImplicit
+        | Token::BaseDefencePercentile => number(0),
+        _ => boolean(false),
+    }
+}
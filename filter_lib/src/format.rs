@@ -0,0 +1,164 @@
+use crate::cst::{parse_cst, CstBlock, CstLine, SyntaxToken};
+use crate::mode_parsing::{KeywordType, Token};
+
+/// Column, after the indent, at which trailing comments on keyword lines
+/// start. Matches the layout of NeverSink's filters.
+pub const COMMENT_COLUMN: usize = 40;
+
+/// Canonical layout of a filter: headers unindented, keywords indented with
+/// one tab and ordered conditions before actions, string lists quoted, single
+/// spaces between values and trailing comments aligned to `COMMENT_COLUMN`.
+/// Comments and blank lines are kept, and so are lines with words the lexer
+/// cannot read as single tokens.
+pub fn format(source: &str) -> String {
+    let cst = parse_cst(source);
+    let mut out = String::new();
+    for block in cst.blocks.iter() {
+        format_block(block, &mut out);
+    }
+    out
+}
+
+pub fn is_formatted(source: &str) -> bool {
+    format(source) == source
+}
+
+fn format_block(block: &CstBlock, out: &mut String) {
+    let mut lines = block.lines.iter();
+    match block.header() {
+        Some(header) => {
+            lines.next();
+            format_header_line(header, out);
+        }
+        None => {
+            // nothing before the first Show or Hide is reordered, and a
+            // legacy `Continue` header or an `Import` there is left as a
+            // header
+            for line in lines {
+                let token = line.keyword().map(|k| &k.token);
+                if token == Some(&Token::Continue) || token == Some(&Token::Import) {
+                    format_header_line(line, out);
+                } else {
+                    format_line(line, out);
+                }
+            }
+            return;
+        }
+    }
+
+    // each keyword line takes the comment and blank lines above it along
+    // when it is reordered
+    let mut groups: Vec<(u8, Vec<&CstLine>)> = vec![];
+    let mut pending: Vec<&CstLine> = vec![];
+    for line in lines {
+        pending.push(line);
+        if let Some(keyword) = line.keyword() {
+            groups.push((rank(&keyword.token), std::mem::take(&mut pending)));
+        }
+    }
+    groups.sort_by_key(|(rank, _)| *rank);
+    let ordered: Vec<_> = groups
+        .into_iter()
+        .flat_map(|(_, g)| g)
+        .chain(pending)
+        .collect();
+    for (i, line) in ordered.iter().enumerate() {
+        format_line(line, out);
+        // the last line of a file has no line ending, and needs one if it
+        // was moved up
+        if line.line_end.is_empty() && i + 1 < ordered.len() {
+            out.push_str(&block.lines[0].line_end);
+        }
+    }
+}
+
+fn format_line(line: &CstLine, out: &mut String) {
+    if line.tokens.is_empty() {
+        format_trivia_line(line, out);
+    } else {
+        format_keyword_line(line, out);
+    }
+}
+
+fn format_header_line(line: &CstLine, out: &mut String) {
+    if !is_plain(line) {
+        out.push_str(&line.to_string());
+        return;
+    }
+    out.push_str(&tokens_text(line));
+    if let Some(comment) = line.comment() {
+        out.push(' ');
+        out.push_str(comment.text.trim_end());
+    }
+    out.push_str(&line.line_end);
+}
+
+fn rank(token: &Token) -> u8 {
+    match token.keyword_type() {
+        Some(KeywordType::Actions) => 1,
+        Some(KeywordType::Flow) => 2,
+        _ => 0,
+    }
+}
+
+fn format_keyword_line(line: &CstLine, out: &mut String) {
+    if !is_plain(line) {
+        out.push_str(&line.to_string());
+        return;
+    }
+    let code = format!("\t{}", tokens_text(line));
+    out.push_str(&code);
+    if let Some(comment) = line.comment() {
+        let width = code.chars().count() - 1;
+        out.push_str(&" ".repeat(COMMENT_COLUMN.saturating_sub(width).max(1)));
+        out.push_str(comment.text.trim_end());
+    }
+    out.push_str(&line.line_end);
+}
+
+fn format_trivia_line(line: &CstLine, out: &mut String) {
+    if let Some(comment) = line.comment() {
+        if !line.trailing_trivia[0].text.starts_with('#') {
+            out.push('\t');
+        }
+        out.push_str(comment.text.trim_end());
+    }
+    out.push_str(&line.line_end);
+}
+
+/// Whether every token of the line is a whole word the lexer understood.
+/// Words like `Two-Hand` or `Maelström` lex as several tokens around an
+/// error, and lines with them are left as they are rather than guessed at.
+fn is_plain(line: &CstLine) -> bool {
+    line.tokens.iter().enumerate().all(|(i, token)| {
+        token.token != Token::Error && (i == 0 || !token.leading_trivia.is_empty())
+    })
+}
+
+fn tokens_text(line: &CstLine) -> String {
+    let quote = line.keyword().is_some_and(|k| takes_strings(&k.token));
+    let words: Vec<_> = line.tokens.iter().map(|t| token_text(t, quote)).collect();
+    words.join(" ")
+}
+
+fn token_text(token: &SyntaxToken, quote: bool) -> String {
+    match token.token {
+        Token::Text(_) if quote => format!("\"{}\"", token.text),
+        _ => token.text.clone(),
+    }
+}
+
+/// Keywords whose values are lists of names.
+pub fn takes_strings(token: &Token) -> bool {
+    matches!(
+        token,
+        Token::Class
+            | Token::BaseType
+            | Token::Prophecy
+            | Token::HasExplicitMod
+            | Token::HasEnchantment
+            | Token::EnchantmentPassiveNode
+            | Token::ArchnemesisMod
+            | Token::CustomAlertSound
+    )
+}
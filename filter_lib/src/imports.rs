@@ -0,0 +1,175 @@
+use crate::diagnostics::{Diagnostic, Severity};
+use crate::mode_parsing::{parse_with, FilterBlock, ParseOptions};
+use crate::source_map::{FileId, SourceMap};
+use std::collections::HashMap;
+use std::io;
+use std::ops::Range;
+use std::path::{Component, Path, PathBuf};
+
+/// Where `resolve` reads filter files from.
+pub trait FileLoader {
+    fn load(&self, path: &Path) -> io::Result<String>;
+}
+
+/// Reads files from disk.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FsLoader;
+
+impl FileLoader for FsLoader {
+    fn load(&self, path: &Path) -> io::Result<String> {
+        std::fs::read_to_string(path)
+    }
+}
+
+/// Files kept in memory, by path.
+impl FileLoader for HashMap<PathBuf, String> {
+    fn load(&self, path: &Path) -> io::Result<String> {
+        self.get(path)
+            .cloned()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "file not found"))
+    }
+}
+
+/// A block and the file it comes from. Its spans are in that file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImportedBlock {
+    pub block: FilterBlock<'static>,
+    pub file: FileId,
+}
+
+/// A filter with its imports followed, as one list of blocks in the order
+/// the game sees them.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ResolvedFilter {
+    /// Every file that was read, the root first.
+    pub sources: SourceMap,
+    pub blocks: Vec<ImportedBlock>,
+    /// Diagnostics of every file, with `file` set to the one they are in.
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl ResolvedFilter {
+    pub fn has_errors(&self) -> bool {
+        self.diagnostics
+            .iter()
+            .any(|d| d.severity == Severity::Error)
+    }
+}
+
+/// Parses the filter at `path` and every file it imports, in place of the
+/// `Import` lines. Import paths are relative to the directory of the file
+/// they are in. An import that leads back to a file being imported is
+/// reported as a cycle and not followed.
+pub fn resolve(path: &Path, loader: &dyn FileLoader, options: ParseOptions) -> ResolvedFilter {
+    let mut resolver = Resolver {
+        loader,
+        options,
+        stack: vec![],
+        output: ResolvedFilter::default(),
+    };
+    if !resolver.load(&normalize(path), None) {
+        let message = format!("cannot read `{}`", path.display());
+        resolver.error(message, None);
+    }
+    resolver.output
+}
+
+struct Resolver<'a> {
+    loader: &'a dyn FileLoader,
+    options: ParseOptions,
+    /// The files being imported, outermost first.
+    stack: Vec<PathBuf>,
+    output: ResolvedFilter,
+}
+
+/// The file and span of the `Import` line a file is read for.
+type ImportSite = (FileId, Range<usize>);
+
+impl Resolver<'_> {
+    fn load(&mut self, path: &Path, site: Option<ImportSite>) -> bool {
+        if let Some(index) = self.stack.iter().position(|p| p == path) {
+            let cycle: Vec<_> = self.stack[index..]
+                .iter()
+                .chain(std::iter::once(&path.to_path_buf()))
+                .map(|p| p.display().to_string())
+                .collect();
+            self.error(format!("import cycle: {}", cycle.join(" -> ")), site);
+            return true;
+        }
+        let source = match self.loader.load(path) {
+            Ok(source) => source,
+            Err(_) => return false,
+        };
+
+        let file = self
+            .output
+            .sources
+            .add(&path.display().to_string(), &source);
+        let parsed = parse_with(&source, self.options.clone()).into_owned();
+        self.output
+            .diagnostics
+            .extend(parsed.diagnostics.into_iter().map(|d| Diagnostic {
+                file: Some(file),
+                ..d
+            }));
+
+        self.stack.push(path.to_path_buf());
+        let dir = path.parent().unwrap_or_else(|| Path::new(""));
+        for block in parsed.blocks {
+            match &block.import {
+                Some(import) => {
+                    // without a path the parser has already reported it
+                    let span = match &import.path_span {
+                        Some(span) => span.clone(),
+                        None => continue,
+                    };
+                    if import.path.is_empty() {
+                        self.error("empty import path".to_string(), Some((file, span)));
+                        continue;
+                    }
+                    let target = normalize(&dir.join(import.path.as_ref()));
+                    if !self.load(&target, Some((file, span.clone()))) && !import.optional {
+                        let message = format!("cannot read imported file `{}`", import.path);
+                        self.error(message, Some((file, span)));
+                    }
+                }
+                None if block.block.is_none() && block.keywords.is_empty() => {}
+                None => self.output.blocks.push(ImportedBlock { block, file }),
+            }
+        }
+        self.stack.pop();
+        true
+    }
+
+    fn error(&mut self, message: String, site: Option<ImportSite>) {
+        let diagnostic = match site {
+            Some((file, span)) => Diagnostic {
+                file: Some(file),
+                ..Diagnostic::new(
+                    &self.output.sources.file(file).source,
+                    Severity::Error,
+                    message,
+                    span,
+                )
+            },
+            None => Diagnostic::new("", Severity::Error, message, 0..0),
+        };
+        self.output.diagnostics.push(diagnostic);
+    }
+}
+
+/// Removes `.` and `..` components without touching the file system, so the
+/// same file reached by two paths is recognized in a cycle.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir if normalized.file_name().is_some() => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
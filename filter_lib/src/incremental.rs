@@ -0,0 +1,147 @@
+use crate::diagnostics::Diagnostic;
+use crate::mode_parsing::{FilterBlock, ParseOptions, ParseOutput, Parser, Token};
+use logos::Logos;
+use std::ops::Range;
+
+/// Replacement of `range` of the old source by `text`.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct TextEdit {
+    pub range: Range<usize>,
+    pub text: String,
+}
+
+impl TextEdit {
+    pub fn new(range: Range<usize>, text: &str) -> Self {
+        TextEdit {
+            range,
+            text: text.to_string(),
+        }
+    }
+
+    pub fn apply(&self, source: &str) -> String {
+        let mut edited = source.to_string();
+        edited.replace_range(self.range.clone(), &self.text);
+        edited
+    }
+
+    /// How much longer the source is after the edit.
+    fn delta(&self) -> isize {
+        self.text.len() as isize - self.range.len() as isize
+    }
+}
+
+/// Updates `previous`, the parse of the source before `edit`, to the parse of
+/// `source`, the text after it. Only the blocks around the edit are parsed
+/// again; spans of the blocks after them are shifted. Returns the indices of
+/// the reparsed blocks in the updated output.
+///
+/// The result is the same as `parse_with(source, options)`, as long as
+/// `previous` was parsed with the same options.
+pub fn reparse(
+    previous: &mut ParseOutput<'static>,
+    source: &str,
+    edit: &TextEdit,
+    options: ParseOptions,
+) -> Range<usize> {
+    let blocks = &previous.blocks;
+    let delta = edit.delta();
+    let edit_end = edit.range.start + edit.text.len();
+
+    // the edit starts after the header of `first`, not right at its end where
+    // it could run into the header token, and the header lines of `last` and
+    // the blocks after it all start after the edit
+    let first = (1..blocks.len())
+        .rev()
+        .find(|&i| header(&blocks[i]).end < edit.range.start)
+        .unwrap_or(0);
+    let last = (first + 1..blocks.len())
+        .find(|&i| {
+            let start = header(&blocks[i]).start;
+            start >= edit.range.end && line_start(source, shift(start, delta)) > edit_end
+        })
+        .unwrap_or(blocks.len());
+
+    let old_start = if first == 0 {
+        0
+    } else {
+        header(&blocks[first]).start
+    };
+    let old_end = blocks.get(last).map(|b| header(b).start);
+    let new_end = old_end.map_or(source.len(), |end| shift(end, delta));
+
+    let mut parser = Parser::new(source, options);
+    for (token, span) in Token::lexer(&source[old_start..new_end]).spanned() {
+        parser.match_filter(token, old_start + span.start..old_start + span.end);
+    }
+    let mut region = parser.finish().into_owned();
+    if first > 0 {
+        // everything before the header of `first`, which is nothing
+        region.blocks.remove(0);
+    }
+
+    let mut after = previous.blocks.split_off(last);
+    for block in after.iter_mut() {
+        shift_block(block, delta);
+    }
+    previous.blocks.truncate(first);
+    let reparsed = first..first + region.blocks.len();
+    previous.blocks.extend(region.blocks);
+    previous.blocks.extend(after);
+
+    let old_end = old_end.unwrap_or(usize::MAX);
+    let diagnostics = std::mem::take(&mut previous.diagnostics);
+    let (before, rest): (Vec<_>, Vec<_>) = diagnostics
+        .into_iter()
+        .filter(|d| d.span.start < old_start || d.span.start >= old_end)
+        .partition(|d| d.span.start < old_start);
+    previous.diagnostics = before;
+    previous.diagnostics.extend(region.diagnostics);
+    previous.diagnostics.extend(rest.into_iter().map(|d| {
+        let span = shift_range(&d.span, delta);
+        Diagnostic::new(source, d.severity, d.message, span)
+    }));
+    reparsed
+}
+
+fn header(block: &FilterBlock) -> Range<usize> {
+    block.bspan.clone().unwrap_or(0..0)
+}
+
+fn line_start(source: &str, offset: usize) -> usize {
+    source[..offset].rfind('\n').map_or(0, |i| i + 1)
+}
+
+fn shift(offset: usize, delta: isize) -> usize {
+    (offset as isize + delta) as usize
+}
+
+fn shift_range(range: &Range<usize>, delta: isize) -> Range<usize> {
+    shift(range.start, delta)..shift(range.end, delta)
+}
+
+fn shift_span(span: &mut Option<Range<usize>>, delta: isize) {
+    if let Some(range) = span {
+        *range = shift_range(range, delta);
+    }
+}
+
+fn shift_block(block: &mut FilterBlock, delta: isize) {
+    shift_span(&mut block.bspan, delta);
+    shift_span(&mut block.continue_span, delta);
+    if let Some(import) = &mut block.import {
+        shift_span(&mut import.path_span, delta);
+    }
+    for tag in block.tags.tags.values_mut() {
+        shift_span(&mut tag.span, delta);
+    }
+    if let Some(comment) = &mut block.comment {
+        shift_span(&mut comment.span, delta);
+    }
+    for keyword in block.keywords.iter_mut() {
+        shift_span(&mut keyword.span, delta);
+        let values = keyword.operator.iter_mut().chain(keyword.value.iter_mut());
+        for value in values.chain(keyword.annotation.iter_mut()) {
+            shift_span(&mut value.span, delta);
+        }
+    }
+}
@@ -0,0 +1,270 @@
+use crate::ast::{Condition, Influence, Op, Rarity, SocketSpec, Statement};
+use crate::mode_parsing::{FilterBlock, KeywordType, TokenAndSpan};
+
+/// An item as the filter sees it when it drops.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Item {
+    pub class: String,
+    pub base_type: String,
+    pub rarity: Rarity,
+    pub item_level: u32,
+    pub drop_level: u32,
+    pub quality: u32,
+    /// Socket colors, with `-` between linked sockets and a space between
+    /// groups, as in `R-G-B W`.
+    pub sockets: String,
+    pub influences: Vec<Influence>,
+    pub identified: bool,
+    pub corrupted: bool,
+    pub mirrored: bool,
+    pub fractured: bool,
+    pub synthesised: bool,
+    pub replica: bool,
+    pub stack_size: u32,
+    pub gem_level: u32,
+    pub map_tier: u32,
+    pub explicit_mods: Vec<String>,
+    pub enchantments: Vec<String>,
+    pub width: u32,
+    pub height: u32,
+    /// Level of the area the item drops in.
+    pub area_level: u32,
+}
+
+impl Default for Item {
+    fn default() -> Self {
+        Item {
+            class: String::new(),
+            base_type: String::new(),
+            rarity: Rarity::Normal,
+            item_level: 1,
+            drop_level: 1,
+            quality: 0,
+            sockets: String::new(),
+            influences: vec![],
+            identified: false,
+            corrupted: false,
+            mirrored: false,
+            fractured: false,
+            synthesised: false,
+            replica: false,
+            stack_size: 1,
+            gem_level: 0,
+            map_tier: 0,
+            explicit_mods: vec![],
+            enchantments: vec![],
+            width: 1,
+            height: 1,
+            area_level: 1,
+        }
+    }
+}
+
+impl Item {
+    pub fn new(class: &str, base_type: &str) -> Self {
+        Item {
+            class: class.to_string(),
+            base_type: base_type.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// The linked groups of `sockets`, each as its socket colors.
+    pub fn socket_groups(&self) -> Vec<String> {
+        self.sockets
+            .split_whitespace()
+            .map(|group| group.split('-').collect())
+            .collect()
+    }
+
+    pub fn socket_count(&self) -> u32 {
+        self.socket_groups().iter().map(|g| g.len() as u32).sum()
+    }
+
+    /// Size of the largest linked group.
+    pub fn links(&self) -> u32 {
+        let groups = self.socket_groups();
+        groups.iter().map(|g| g.len() as u32).max().unwrap_or(0)
+    }
+}
+
+impl FilterBlock<'_> {
+    /// Whether every condition of the block holds for `item`. Actions are
+    /// not looked at, even ones with invalid values.
+    pub fn matches(&self, item: &Item) -> bool {
+        self.keywords
+            .iter()
+            .filter(|k| matches!(k.token.keyword_type(), Some(KeywordType::Conditions)))
+            .all(|k| k.matches(item))
+    }
+}
+
+impl TokenAndSpan<'_> {
+    /// Whether the keyword, a condition, holds for `item`. A condition with
+    /// invalid values never holds. Conditions on what `Item` does not have,
+    /// like `BlightedMap` or `CorruptedMods`, see `false`, `0` or no names.
+    ///
+    /// Names match as the game matches them: without `==` a name matches any
+    /// text that contains it, and either way case is ignored. `!=` or `!`
+    /// holds when no name matches, and a count before the names, as in
+    /// `HasExplicitMod >= 2 "of Haast" "of Tzteosh"`, is compared to how
+    /// many of the item's texts match.
+    pub fn matches(&self, item: &Item) -> bool {
+        let condition = match self.statement() {
+            Ok(Statement::Condition(condition)) => condition,
+            _ => return false,
+        };
+        let op = self.op();
+        let flag = |wanted: bool, actual: bool| wanted == actual;
+        match &condition {
+            Condition::AreaLevel(op, n) => compare(item.area_level, *op, *n),
+            Condition::ItemLevel(op, n) => compare(item.item_level, *op, *n),
+            Condition::DropLevel(op, n) => compare(item.drop_level, *op, *n),
+            Condition::Quality(op, n) => compare(item.quality, *op, *n),
+            Condition::Rarity(op, rarities) => {
+                any_value(rarities, *op, |r| compare(item.rarity, *op, *r))
+            }
+            Condition::Class { exact, names } => {
+                self.names_match(*exact, None, names, &[&item.class])
+            }
+            Condition::BaseType { exact, names } => {
+                self.names_match(*exact, None, names, &[&item.base_type])
+            }
+            Condition::HasExplicitMod {
+                exact,
+                count,
+                names,
+            } => self.names_match(*exact, *count, names, &as_strs(&item.explicit_mods)),
+            Condition::HasEnchantment {
+                exact,
+                count,
+                names,
+            } => self.names_match(*exact, *count, names, &as_strs(&item.enchantments)),
+            Condition::Prophecy { exact, names }
+            | Condition::EnchantmentPassiveNode { exact, names }
+            | Condition::ArchnemesisMod { exact, names } => {
+                self.names_match(*exact, None, names, &[])
+            }
+            Condition::GemQualityType(names) => self.names_match(false, None, names, &[]),
+            Condition::LinkedSockets(op, n) => compare(item.links(), *op, *n),
+            Condition::Sockets(op, specs) => any_value(specs, *op, |spec| {
+                sockets_match(&item.sockets.replace([' ', '-'], ""), *op, spec)
+            }),
+            Condition::SocketGroup(op, specs) => any_value(specs, *op, |spec| {
+                item.socket_groups()
+                    .iter()
+                    .any(|group| sockets_match(group, *op, spec))
+            }),
+            Condition::Height(op, n) => compare(item.height, *op, *n),
+            Condition::Width(op, n) => compare(item.width, *op, *n),
+            Condition::AnyEnchantment(b) => flag(*b, !item.enchantments.is_empty()),
+            Condition::StackSize(op, n) => compare(item.stack_size, *op, *n),
+            Condition::GemLevel(op, n) => compare(item.gem_level, *op, *n),
+            Condition::Identified(b) => flag(*b, item.identified),
+            Condition::Corrupted(b) => flag(*b, item.corrupted),
+            Condition::Mirrored(b) => flag(*b, item.mirrored),
+            Condition::ElderItem(b) => flag(*b, item.influences.contains(&Influence::Elder)),
+            Condition::ShaperItem(b) => flag(*b, item.influences.contains(&Influence::Shaper)),
+            Condition::HasInfluence(influences) => {
+                let has = |i: &Influence| match i {
+                    Influence::None => item.influences.is_empty(),
+                    i => item.influences.contains(i),
+                };
+                if op == Op::Exact {
+                    influences.iter().all(has)
+                } else {
+                    influences.iter().any(has)
+                }
+            }
+            Condition::FracturedItem(b) => flag(*b, item.fractured),
+            Condition::SynthesisedItem(b) => flag(*b, item.synthesised),
+            Condition::MapTier(op, n) => compare(item.map_tier, *op, *n),
+            Condition::Replica(b) => flag(*b, item.replica),
+            Condition::ShapedMap(b)
+            | Condition::BlightedMap(b)
+            | Condition::ElderMap(b)
+            | Condition::AlternateQuality(b)
+            | Condition::UberBlightedMap(b)
+            | Condition::Scourged(b)
+            | Condition::TransfiguredGem(b) => flag(*b, false),
+            Condition::CorruptedMods(op, n)
+            | Condition::EnchantmentPassiveNum(op, n)
+            | Condition::HasSearingExarchImplicit(op, n)
+            | Condition::HasEaterOfWorldsImplicit(op, n)
+            | Condition::BaseDefencePercentile(op, n) => compare(0, *op, *n),
+        }
+    }
+
+    fn names_match(
+        &self,
+        exact: bool,
+        count: Option<u32>,
+        names: &[String],
+        texts: &[&str],
+    ) -> bool {
+        let matches = |text: &str| {
+            let text = text.to_lowercase();
+            names.iter().any(|name| {
+                let name = name.to_lowercase();
+                if exact {
+                    text == name
+                } else {
+                    text.contains(&name)
+                }
+            })
+        };
+        match (count, self.op()) {
+            (Some(count), op) => compare(
+                texts.iter().filter(|t| matches(t)).count() as u32,
+                op,
+                count,
+            ),
+            (None, Op::NotEq) => !texts.iter().any(|t| matches(t)),
+            (None, _) => texts.iter().any(|t| matches(t)),
+        }
+    }
+}
+
+/// `actual op expected`, with `=` and `==` both meaning equal.
+pub fn compare<T: Ord>(actual: T, op: Op, expected: T) -> bool {
+    match op {
+        Op::Eq | Op::Exact => actual == expected,
+        Op::NotEq => actual != expected,
+        Op::Lt => actual < expected,
+        Op::Le => actual <= expected,
+        Op::Gt => actual > expected,
+        Op::Ge => actual >= expected,
+    }
+}
+
+/// A list of values holds if any of them does, or for `!=` if all do.
+fn any_value<T>(values: &[T], op: Op, f: impl Fn(&T) -> bool) -> bool {
+    if op == Op::NotEq {
+        values.iter().all(f)
+    } else {
+        values.iter().any(f)
+    }
+}
+
+/// Whether sockets, given as their colors, have the count of `spec` and at
+/// least the colors it asks for.
+fn sockets_match(sockets: &str, op: Op, spec: &SocketSpec) -> bool {
+    let color = |c: char| sockets.chars().filter(|s| *s == c).count() as u32;
+    let colors = color('R') >= spec.red
+        && color('G') >= spec.green
+        && color('B') >= spec.blue
+        && color('W') >= spec.white
+        && color('A') >= spec.abyss
+        && color('D') >= spec.delve;
+    match spec.count {
+        Some(count) => compare(sockets.len() as u32, op, count) && colors,
+        None if op == Op::NotEq => !colors,
+        None => colors,
+    }
+}
+
+fn as_strs(texts: &[String]) -> Vec<&str> {
+    texts.iter().map(String::as_str).collect()
+}
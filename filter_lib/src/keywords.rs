@@ -0,0 +1,420 @@
+use crate::mode_parsing::Token;
+use std::fmt;
+
+/// A game patch, like `3.8` for Blight.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, PartialOrd, Ord)]
+pub struct GameVersion {
+    pub major: u16,
+    pub minor: u16,
+}
+
+impl GameVersion {
+    pub const fn new(major: u16, minor: u16) -> Self {
+        GameVersion { major, minor }
+    }
+}
+
+impl fmt::Display for GameVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
+    }
+}
+
+/// When a keyword was added to the game, and removed if it was.
+#[derive(Clone, Debug, PartialEq)]
+pub struct KeywordInfo {
+    pub token: Token<'static>,
+    pub name: &'static str,
+    pub added: GameVersion,
+    pub removed: Option<GameVersion>,
+}
+
+impl KeywordInfo {
+    pub fn available_in(&self, version: GameVersion) -> bool {
+        self.added <= version && self.removed.is_none_or(|r| version < r)
+    }
+}
+
+const fn v(major: u16, minor: u16) -> GameVersion {
+    GameVersion::new(major, minor)
+}
+
+/// Every keyword the lexer knows about, with the patch that introduced it.
+pub static KEYWORDS: &[KeywordInfo] = &[
+    // blocks
+    KeywordInfo {
+        token: Token::Show,
+        name: "Show",
+        added: v(1, 2),
+        removed: None,
+    },
+    KeywordInfo {
+        token: Token::Hide,
+        name: "Hide",
+        added: v(1, 2),
+        removed: None,
+    },
+    KeywordInfo {
+        token: Token::Continue,
+        name: "Continue",
+        added: v(3, 8),
+        removed: None,
+    },
+    KeywordInfo {
+        token: Token::Import,
+        name: "Import",
+        added: v(3, 23),
+        removed: None,
+    },
+    // conditions
+    KeywordInfo {
+        token: Token::AreaLevel,
+        name: "AreaLevel",
+        added: v(3, 11),
+        removed: None,
+    },
+    KeywordInfo {
+        token: Token::ItemLevel,
+        name: "ItemLevel",
+        added: v(1, 2),
+        removed: None,
+    },
+    KeywordInfo {
+        token: Token::DropLevel,
+        name: "DropLevel",
+        added: v(1, 2),
+        removed: None,
+    },
+    KeywordInfo {
+        token: Token::Quality,
+        name: "Quality",
+        added: v(1, 2),
+        removed: None,
+    },
+    KeywordInfo {
+        token: Token::Rarity,
+        name: "Rarity",
+        added: v(1, 2),
+        removed: None,
+    },
+    KeywordInfo {
+        token: Token::Class,
+        name: "Class",
+        added: v(1, 2),
+        removed: None,
+    },
+    KeywordInfo {
+        token: Token::BaseType,
+        name: "BaseType",
+        added: v(1, 2),
+        removed: None,
+    },
+    KeywordInfo {
+        token: Token::Prophecy,
+        name: "Prophecy",
+        added: v(3, 3),
+        removed: Some(v(3, 17)),
+    },
+    KeywordInfo {
+        token: Token::LinkedSockets,
+        name: "LinkedSockets",
+        added: v(1, 2),
+        removed: None,
+    },
+    KeywordInfo {
+        token: Token::SocketGroup,
+        name: "SocketGroup",
+        added: v(1, 2),
+        removed: None,
+    },
+    KeywordInfo {
+        token: Token::Sockets,
+        name: "Sockets",
+        added: v(1, 2),
+        removed: None,
+    },
+    KeywordInfo {
+        token: Token::Height,
+        name: "Height",
+        added: v(2, 1),
+        removed: None,
+    },
+    KeywordInfo {
+        token: Token::Width,
+        name: "Width",
+        added: v(2, 1),
+        removed: None,
+    },
+    KeywordInfo {
+        token: Token::HasExplicitMod,
+        name: "HasExplicitMod",
+        added: v(3, 2),
+        removed: None,
+    },
+    KeywordInfo {
+        token: Token::AnyEnchantment,
+        name: "AnyEnchantment",
+        added: v(3, 4),
+        removed: None,
+    },
+    KeywordInfo {
+        token: Token::HasEnchantment,
+        name: "HasEnchantment",
+        added: v(3, 4),
+        removed: None,
+    },
+    KeywordInfo {
+        token: Token::StackSize,
+        name: "StackSize",
+        added: v(3, 3),
+        removed: None,
+    },
+    KeywordInfo {
+        token: Token::GemLevel,
+        name: "GemLevel",
+        added: v(3, 3),
+        removed: None,
+    },
+    KeywordInfo {
+        token: Token::Identified,
+        name: "Identified",
+        added: v(2, 4),
+        removed: None,
+    },
+    KeywordInfo {
+        token: Token::Corrupted,
+        name: "Corrupted",
+        added: v(2, 4),
+        removed: None,
+    },
+    KeywordInfo {
+        token: Token::CorruptedMods,
+        name: "CorruptedMods",
+        added: v(3, 5),
+        removed: None,
+    },
+    KeywordInfo {
+        token: Token::Mirrored,
+        name: "Mirrored",
+        added: v(3, 3),
+        removed: None,
+    },
+    KeywordInfo {
+        token: Token::ElderItem,
+        name: "ElderItem",
+        added: v(3, 1),
+        removed: Some(v(3, 10)),
+    },
+    KeywordInfo {
+        token: Token::ShaperItem,
+        name: "ShaperItem",
+        added: v(3, 1),
+        removed: Some(v(3, 10)),
+    },
+    KeywordInfo {
+        token: Token::HasInfluence,
+        name: "HasInfluence",
+        added: v(3, 9),
+        removed: None,
+    },
+    KeywordInfo {
+        token: Token::FracturedItem,
+        name: "FracturedItem",
+        added: v(3, 6),
+        removed: None,
+    },
+    KeywordInfo {
+        token: Token::SynthesisedItem,
+        name: "SynthesisedItem",
+        added: v(3, 6),
+        removed: None,
+    },
+    KeywordInfo {
+        token: Token::ShapedMap,
+        name: "ShapedMap",
+        added: v(2, 4),
+        removed: Some(v(3, 9)),
+    },
+    KeywordInfo {
+        token: Token::MapTier,
+        name: "MapTier",
+        added: v(3, 1),
+        removed: None,
+    },
+    KeywordInfo {
+        token: Token::BlightedMap,
+        name: "BlightedMap",
+        added: v(3, 8),
+        removed: None,
+    },
+    KeywordInfo {
+        token: Token::ElderMap,
+        name: "ElderMap",
+        added: v(3, 5),
+        removed: Some(v(3, 9)),
+    },
+    KeywordInfo {
+        token: Token::Replica,
+        name: "Replica",
+        added: v(3, 12),
+        removed: None,
+    },
+    KeywordInfo {
+        token: Token::GemQualityType,
+        name: "GemQualityType",
+        added: v(3, 12),
+        removed: Some(v(3, 23)),
+    },
+    KeywordInfo {
+        token: Token::AlternateQuality,
+        name: "AlternateQuality",
+        added: v(3, 12),
+        removed: Some(v(3, 23)),
+    },
+    KeywordInfo {
+        token: Token::EnchantmentPassiveNode,
+        name: "EnchantmentPassiveNode",
+        added: v(3, 10),
+        removed: None,
+    },
+    KeywordInfo {
+        token: Token::EnchantmentPassiveNum,
+        name: "EnchantmentPassiveNum",
+        added: v(3, 10),
+        removed: None,
+    },
+    KeywordInfo {
+        token: Token::HasSearingExarchImplicit,
+        name: "HasSearingExarchImplicit",
+        added: v(3, 17),
+        removed: None,
+    },
+    KeywordInfo {
+        token: Token::HasEaterOfWorldsImplicit,
+        name: "HasEaterOfWorldsImplicit",
+        added: v(3, 17),
+        removed: None,
+    },
+    KeywordInfo {
+        token: Token::ArchnemesisMod,
+        name: "ArchnemesisMod",
+        added: v(3, 17),
+        removed: Some(v(3, 18)),
+    },
+    KeywordInfo {
+        token: Token::UberBlightedMap,
+        name: "UberBlightedMap",
+        added: v(3, 17),
+        removed: None,
+    },
+    KeywordInfo {
+        token: Token::BaseDefencePercentile,
+        name: "BaseDefencePercentile",
+        added: v(3, 19),
+        removed: None,
+    },
+    KeywordInfo {
+        token: Token::Scourged,
+        name: "Scourged",
+        added: v(3, 16),
+        removed: Some(v(3, 17)),
+    },
+    KeywordInfo {
+        token: Token::TransfiguredGem,
+        name: "TransfiguredGem",
+        added: v(3, 23),
+        removed: None,
+    },
+    // actions
+    KeywordInfo {
+        token: Token::SetBorderColor,
+        name: "SetBorderColor",
+        added: v(1, 2),
+        removed: None,
+    },
+    KeywordInfo {
+        token: Token::SetTextColor,
+        name: "SetTextColor",
+        added: v(1, 2),
+        removed: None,
+    },
+    KeywordInfo {
+        token: Token::SetBackgroundColor,
+        name: "SetBackgroundColor",
+        added: v(1, 2),
+        removed: None,
+    },
+    KeywordInfo {
+        token: Token::SetFontSize,
+        name: "SetFontSize",
+        added: v(2, 1),
+        removed: None,
+    },
+    KeywordInfo {
+        token: Token::PlayAlertSound,
+        name: "PlayAlertSound",
+        added: v(1, 3),
+        removed: None,
+    },
+    KeywordInfo {
+        token: Token::PlayAlertSoundPositional,
+        name: "PlayAlertSoundPositional",
+        added: v(3, 1),
+        removed: None,
+    },
+    KeywordInfo {
+        token: Token::DisableDropSound,
+        name: "DisableDropSound",
+        added: v(3, 5),
+        removed: None,
+    },
+    KeywordInfo {
+        token: Token::EnableDropSound,
+        name: "EnableDropSound",
+        added: v(3, 10),
+        removed: None,
+    },
+    KeywordInfo {
+        token: Token::CustomAlertSound,
+        name: "CustomAlertSound",
+        added: v(3, 5),
+        removed: None,
+    },
+    KeywordInfo {
+        token: Token::MinimapIcon,
+        name: "MinimapIcon",
+        added: v(3, 3),
+        removed: None,
+    },
+    KeywordInfo {
+        token: Token::PlayEffect,
+        name: "PlayEffect",
+        added: v(3, 3),
+        removed: None,
+    },
+];
+
+pub fn keyword_info(token: &Token) -> Option<&'static KeywordInfo> {
+    KEYWORDS.iter().find(|k| &k.token == token)
+}
+
+pub fn keyword_by_name(name: &str) -> Option<&'static KeywordInfo> {
+    KEYWORDS.iter().find(|k| k.name == name)
+}
+
+/// Why `token` can not be used in a filter for `version`, if it can't.
+pub fn check(token: &Token, version: GameVersion) -> Option<String> {
+    let info = keyword_info(token)?;
+    if version < info.added {
+        return Some(format!(
+            "`{}` needs game version {} or later, the filter targets {}",
+            info.name, info.added, version
+        ));
+    }
+    info.removed.filter(|r| version >= *r).map(|removed| {
+        format!(
+            "`{}` was removed in game version {}, the filter targets {}",
+            info.name, removed, version
+        )
+    })
+}
@@ -1,4 +1,18 @@
+pub mod ast;
+pub mod color;
+pub mod cst;
+pub mod diagnostics;
+pub mod encoding;
+pub mod evaluate;
+pub mod explain;
+pub mod format;
+pub mod imports;
+pub mod incremental;
+pub mod item;
+pub mod keywords;
 pub mod logos_parsing;
-use logos_parsing::*;
 pub mod mode_parsing;
-use mode_parsing::*;
+pub mod printer;
+pub mod sections;
+pub mod source_map;
+pub mod style;
@@ -118,9 +118,9 @@ pub fn parse() -> Vec<FilterBlock> {
         block: None,
         hasexplicitmod: None,
     };
-    let mut lex = Token::lexer(filter_file).spanned();
+    let lex = Token::lexer(filter_file).spanned();
 
-    let thing = lex
+    let _thing = lex
         .map(|x| match x.0 {
             Token::Error => {}
             Token::Show => {
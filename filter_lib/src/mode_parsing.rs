@@ -1,7 +1,14 @@
-use logos::{Lexer, Logos};
+use crate::diagnostics::{Diagnostic, Severity};
+use crate::keywords::{self, GameVersion};
+use logos::{Logos, SpannedIter};
+use std::borrow::Cow;
+use std::collections::BTreeMap;
 
-#[derive(Clone, Debug, Eq, PartialEq, Hash, Logos)]
-pub enum Token {
+#[derive(Clone, Debug, Default, Eq, PartialEq, Hash, Logos)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum Token<'src> {
+    #[default]
     #[error]
     Error,
     #[token("Show")]
@@ -10,13 +17,34 @@ pub enum Token {
     Hide,
     #[token("Continue")]
     Continue,
-    #[token("#", ignore_comments)]
+    #[token("Import")]
+    Import,
+    #[regex("#[^\r\n]*")]
     Hash,
-    #[regex(" | |", logos::skip)]
+    // `\r` so that `\r\n` line endings lex like `\n`
+    #[regex("[ \t\r]+", logos::skip)]
     Skip,
     #[token("\n")]
     EndLine,
 
+    // Operators
+    #[token("<")]
+    Less,
+    #[token("<=")]
+    LessEqual,
+    #[token(">")]
+    Greater,
+    #[token(">=")]
+    GreaterEqual,
+    #[token("=")]
+    Equal,
+    #[token("==")]
+    ExactEqual,
+    #[token("!")]
+    Not,
+    #[token("!=")]
+    NotEqual,
+
     // Conditions
     #[token("AreaLevel")]
     AreaLevel,
@@ -76,6 +104,34 @@ pub enum Token {
     ShapedMap,
     #[token("MapTier")]
     MapTier,
+    #[token("BlightedMap")]
+    BlightedMap,
+    #[token("ElderMap")]
+    ElderMap,
+    #[token("Replica")]
+    Replica,
+    #[token("GemQualityType")]
+    GemQualityType,
+    #[token("AlternateQuality")]
+    AlternateQuality,
+    #[token("EnchantmentPassiveNode")]
+    EnchantmentPassiveNode,
+    #[token("EnchantmentPassiveNum")]
+    EnchantmentPassiveNum,
+    #[token("HasSearingExarchImplicit")]
+    HasSearingExarchImplicit,
+    #[token("HasEaterOfWorldsImplicit")]
+    HasEaterOfWorldsImplicit,
+    #[token("ArchnemesisMod")]
+    ArchnemesisMod,
+    #[token("UberBlightedMap")]
+    UberBlightedMap,
+    #[token("BaseDefencePercentile")]
+    BaseDefencePercentile,
+    #[token("Scourged")]
+    Scourged,
+    #[token("TransfiguredGem")]
+    TransfiguredGem,
 
     // Actions
     #[token("SetBorderColor")]
@@ -92,6 +148,8 @@ pub enum Token {
     PlayAlertSoundPositional,
     #[token("DisableDropSound")]
     DisableDropSound,
+    #[token("EnableDropSound")]
+    EnableDropSound,
     #[token("CustomAlertSound")]
     CustomAlertSound,
     #[token("MinimapIcon")]
@@ -100,25 +158,28 @@ pub enum Token {
     PlayEffect,
 
     // Values
-    #[regex("[0-9]+", |s| s.slice().to_string())]
-    Numbers(String),
-    #[regex("\"([^\"]*)\"", |s| s.slice().to_string())]
-    Quotes(String),
-    #[regex("true|false|True|False", |s| s.slice().to_string())]
-    Boolean(String),
-    #[regex("[a-zA-Z]+", |s| s.slice().to_string())]
-    Text(String),
+    #[regex("-?[0-9]+", |s| Cow::Borrowed(s.slice()))]
+    Numbers(Cow<'src, str>),
+    #[regex("\"([^\"\n]*)\"", |s| Cow::Borrowed(s.slice()))]
+    Quotes(Cow<'src, str>),
+    #[regex("true|false|True|False", |s| Cow::Borrowed(s.slice()))]
+    Boolean(Cow<'src, str>),
+    #[regex("[a-zA-Z]+", |s| Cow::Borrowed(s.slice()))]
+    Text(Cow<'src, str>),
 }
-pub enum KeywordType {
+pub enum KeywordType<'src> {
     Conditions,
     Actions,
     Block,
+    // statements that change how blocks are evaluated, like `Continue`
+    Flow,
+    Import,
     Operations,
-    Values(String),
+    Values(Cow<'src, str>),
 }
 
-impl Token {
-    pub fn keyword_type(&self) -> Option<KeywordType> {
+impl<'src> Token<'src> {
+    pub fn keyword_type(&self) -> Option<KeywordType<'src>> {
         match self {
             Token::Error => None,
             Token::Hash => None,
@@ -127,7 +188,17 @@ impl Token {
             // blocks
             Token::Show => Some(KeywordType::Block),
             Token::Hide => Some(KeywordType::Block),
-            Token::Continue => Some(KeywordType::Block),
+            Token::Continue => Some(KeywordType::Flow),
+            Token::Import => Some(KeywordType::Import),
+            // operators
+            Token::Less => Some(KeywordType::Operations),
+            Token::LessEqual => Some(KeywordType::Operations),
+            Token::Greater => Some(KeywordType::Operations),
+            Token::GreaterEqual => Some(KeywordType::Operations),
+            Token::Equal => Some(KeywordType::Operations),
+            Token::ExactEqual => Some(KeywordType::Operations),
+            Token::Not => Some(KeywordType::Operations),
+            Token::NotEqual => Some(KeywordType::Operations),
             //contisitons
             Token::AreaLevel => Some(KeywordType::Conditions),
             Token::ItemLevel => Some(KeywordType::Conditions),
@@ -158,6 +229,20 @@ impl Token {
             Token::SynthesisedItem => Some(KeywordType::Conditions),
             Token::ShapedMap => Some(KeywordType::Conditions),
             Token::MapTier => Some(KeywordType::Conditions),
+            Token::BlightedMap => Some(KeywordType::Conditions),
+            Token::ElderMap => Some(KeywordType::Conditions),
+            Token::Replica => Some(KeywordType::Conditions),
+            Token::GemQualityType => Some(KeywordType::Conditions),
+            Token::AlternateQuality => Some(KeywordType::Conditions),
+            Token::EnchantmentPassiveNode => Some(KeywordType::Conditions),
+            Token::EnchantmentPassiveNum => Some(KeywordType::Conditions),
+            Token::HasSearingExarchImplicit => Some(KeywordType::Conditions),
+            Token::HasEaterOfWorldsImplicit => Some(KeywordType::Conditions),
+            Token::ArchnemesisMod => Some(KeywordType::Conditions),
+            Token::UberBlightedMap => Some(KeywordType::Conditions),
+            Token::BaseDefencePercentile => Some(KeywordType::Conditions),
+            Token::Scourged => Some(KeywordType::Conditions),
+            Token::TransfiguredGem => Some(KeywordType::Conditions),
             //actions
             Token::SetBorderColor => Some(KeywordType::Actions),
             Token::SetTextColor => Some(KeywordType::Actions),
@@ -166,120 +251,693 @@ impl Token {
             Token::PlayAlertSound => Some(KeywordType::Actions),
             Token::PlayAlertSoundPositional => Some(KeywordType::Actions),
             Token::DisableDropSound => Some(KeywordType::Actions),
+            Token::EnableDropSound => Some(KeywordType::Actions),
             Token::CustomAlertSound => Some(KeywordType::Actions),
             Token::MinimapIcon => Some(KeywordType::Actions),
             Token::PlayEffect => Some(KeywordType::Actions),
             // values
-            Token::Numbers(s) => Some(KeywordType::Values(s.to_owned())),
-            Token::Quotes(s) => Some(KeywordType::Values(s.to_owned())),
-            Token::Boolean(s) => Some(KeywordType::Values(s.to_owned())),
-            Token::Text(s) => Some(KeywordType::Values(s.to_owned())),
+            Token::Numbers(s) => Some(KeywordType::Values(s.clone())),
+            Token::Quotes(s) => Some(KeywordType::Values(s.clone())),
+            Token::Boolean(s) => Some(KeywordType::Values(s.clone())),
+            Token::Text(s) => Some(KeywordType::Values(s.clone())),
         }
     }
-}
 
-impl Default for Token {
-    fn default() -> Self {
-        Token::Error
+    /// The same token with its value copied out of the source.
+    pub fn into_owned(self) -> Token<'static> {
+        match self {
+            Token::Error => Token::Error,
+            Token::Show => Token::Show,
+            Token::Hide => Token::Hide,
+            Token::Continue => Token::Continue,
+            Token::Import => Token::Import,
+            Token::Hash => Token::Hash,
+            Token::Skip => Token::Skip,
+            Token::EndLine => Token::EndLine,
+            Token::Less => Token::Less,
+            Token::LessEqual => Token::LessEqual,
+            Token::Greater => Token::Greater,
+            Token::GreaterEqual => Token::GreaterEqual,
+            Token::Equal => Token::Equal,
+            Token::ExactEqual => Token::ExactEqual,
+            Token::Not => Token::Not,
+            Token::NotEqual => Token::NotEqual,
+            Token::AreaLevel => Token::AreaLevel,
+            Token::ItemLevel => Token::ItemLevel,
+            Token::DropLevel => Token::DropLevel,
+            Token::Quality => Token::Quality,
+            Token::Rarity => Token::Rarity,
+            Token::Class => Token::Class,
+            Token::BaseType => Token::BaseType,
+            Token::Prophecy => Token::Prophecy,
+            Token::LinkedSockets => Token::LinkedSockets,
+            Token::SocketGroup => Token::SocketGroup,
+            Token::Sockets => Token::Sockets,
+            Token::Height => Token::Height,
+            Token::Width => Token::Width,
+            Token::HasExplicitMod => Token::HasExplicitMod,
+            Token::AnyEnchantment => Token::AnyEnchantment,
+            Token::HasEnchantment => Token::HasEnchantment,
+            Token::StackSize => Token::StackSize,
+            Token::GemLevel => Token::GemLevel,
+            Token::Identified => Token::Identified,
+            Token::Corrupted => Token::Corrupted,
+            Token::CorruptedMods => Token::CorruptedMods,
+            Token::Mirrored => Token::Mirrored,
+            Token::ElderItem => Token::ElderItem,
+            Token::ShaperItem => Token::ShaperItem,
+            Token::HasInfluence => Token::HasInfluence,
+            Token::FracturedItem => Token::FracturedItem,
+            Token::SynthesisedItem => Token::SynthesisedItem,
+            Token::ShapedMap => Token::ShapedMap,
+            Token::MapTier => Token::MapTier,
+            Token::BlightedMap => Token::BlightedMap,
+            Token::ElderMap => Token::ElderMap,
+            Token::Replica => Token::Replica,
+            Token::GemQualityType => Token::GemQualityType,
+            Token::AlternateQuality => Token::AlternateQuality,
+            Token::EnchantmentPassiveNode => Token::EnchantmentPassiveNode,
+            Token::EnchantmentPassiveNum => Token::EnchantmentPassiveNum,
+            Token::HasSearingExarchImplicit => Token::HasSearingExarchImplicit,
+            Token::HasEaterOfWorldsImplicit => Token::HasEaterOfWorldsImplicit,
+            Token::ArchnemesisMod => Token::ArchnemesisMod,
+            Token::UberBlightedMap => Token::UberBlightedMap,
+            Token::BaseDefencePercentile => Token::BaseDefencePercentile,
+            Token::Scourged => Token::Scourged,
+            Token::TransfiguredGem => Token::TransfiguredGem,
+            Token::SetBorderColor => Token::SetBorderColor,
+            Token::SetTextColor => Token::SetTextColor,
+            Token::SetBackgroundColor => Token::SetBackgroundColor,
+            Token::SetFontSize => Token::SetFontSize,
+            Token::PlayAlertSound => Token::PlayAlertSound,
+            Token::PlayAlertSoundPositional => Token::PlayAlertSoundPositional,
+            Token::DisableDropSound => Token::DisableDropSound,
+            Token::EnableDropSound => Token::EnableDropSound,
+            Token::CustomAlertSound => Token::CustomAlertSound,
+            Token::MinimapIcon => Token::MinimapIcon,
+            Token::PlayEffect => Token::PlayEffect,
+            Token::Numbers(s) => Token::Numbers(Cow::Owned(s.into_owned())),
+            Token::Quotes(s) => Token::Quotes(Cow::Owned(s.into_owned())),
+            Token::Boolean(s) => Token::Boolean(Cow::Owned(s.into_owned())),
+            Token::Text(s) => Token::Text(Cow::Owned(s.into_owned())),
+        }
+    }
+
+    pub fn operator_symbol(&self) -> Option<&'static str> {
+        match self {
+            Token::Less => Some("<"),
+            Token::LessEqual => Some("<="),
+            Token::Greater => Some(">"),
+            Token::GreaterEqual => Some(">="),
+            Token::Equal => Some("="),
+            Token::ExactEqual => Some("=="),
+            Token::Not => Some("!"),
+            Token::NotEqual => Some("!="),
+            _ => None,
+        }
     }
 }
 
 #[derive(PartialEq, Debug, Clone, Default)]
-pub struct FilterBlock {
-    pub block: Option<Token>,
-    pub keywords: Vec<TokenAndSpan>,
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct FilterBlock<'src> {
+    pub block: Option<Token<'src>>,
+    pub keywords: Vec<TokenAndSpan<'src>>,
     pub bspan: Option<std::ops::Range<usize>>,
+    pub tags: BlockTags<'src>,
+    /// Trailing comment of the header line, without the `#`. `tags` are read
+    /// from it.
+    pub comment: Option<ValueAndSpan<'src>>,
+    /// Whether the block ends with `Continue`, so that items it matches are
+    /// still checked against the blocks after it.
+    pub continues: bool,
+    pub continue_span: Option<std::ops::Range<usize>>,
+    /// Set for the block of an `Import` line, which has `Token::Import` as
+    /// its header and no keywords.
+    pub import: Option<Import<'src>>,
 }
-impl FilterBlock {
+impl<'src> FilterBlock<'src> {
     pub fn clear(&mut self) -> Self {
         FilterBlock::default()
     }
+
+    pub fn into_owned(self) -> FilterBlock<'static> {
+        FilterBlock {
+            block: self.block.map(Token::into_owned),
+            keywords: self
+                .keywords
+                .into_iter()
+                .map(TokenAndSpan::into_owned)
+                .collect(),
+            bspan: self.bspan,
+            tags: self.tags.into_owned(),
+            comment: self.comment.map(ValueAndSpan::into_owned),
+            continues: self.continues,
+            continue_span: self.continue_span,
+            import: self.import.map(Import::into_owned),
+        }
+    }
 }
 
+/// `Import "path/to/other.filter"`, optionally followed by `Optional`. The
+/// path is relative to the file the line is in.
 #[derive(PartialEq, Debug, Default, Clone)]
-pub struct TokenAndSpan {
-    pub token: Token,
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Import<'src> {
+    pub path: Cow<'src, str>,
+    pub path_span: Option<std::ops::Range<usize>>,
+    /// A missing file is skipped instead of being an error.
+    pub optional: bool,
+}
+impl<'src> Import<'src> {
+    pub fn into_owned(self) -> Import<'static> {
+        Import {
+            path: Cow::Owned(self.path.into_owned()),
+            path_span: self.path_span,
+            optional: self.optional,
+        }
+    }
+}
+
+#[derive(PartialEq, Debug, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct TokenAndSpan<'src> {
+    pub token: Token<'src>,
     pub span: Option<std::ops::Range<usize>>,
-    pub value: Vec<ValueAndSpan>,
+    pub operator: Option<ValueAndSpan<'src>>,
+    pub value: Vec<ValueAndSpan<'src>>,
+    /// Trailing comment of the keyword's line, without the `#`.
+    pub annotation: Option<ValueAndSpan<'src>>,
+}
+impl<'src> TokenAndSpan<'src> {
+    pub fn into_owned(self) -> TokenAndSpan<'static> {
+        TokenAndSpan {
+            token: self.token.into_owned(),
+            span: self.span,
+            operator: self.operator.map(ValueAndSpan::into_owned),
+            value: self
+                .value
+                .into_iter()
+                .map(ValueAndSpan::into_owned)
+                .collect(),
+            annotation: self.annotation.map(ValueAndSpan::into_owned),
+        }
+    }
+
+    /// The label and name of an annotation such as `# TEXTCOLOR: Rare 75+`.
+    pub fn style_name(&self) -> Option<(&str, &str)> {
+        let annotation = self.annotation.as_ref()?;
+        let (label, name) = annotation.value.split_once(':')?;
+        Some((label.trim(), name.trim()))
+    }
 }
 #[derive(PartialEq, Debug, Default, Clone)]
-pub struct ValueAndSpan {
-    pub token: Token,
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ValueAndSpan<'src> {
+    pub token: Token<'src>,
     pub span: Option<std::ops::Range<usize>>,
-    pub value: String,
+    pub value: Cow<'src, str>,
+}
+impl<'src> ValueAndSpan<'src> {
+    pub fn into_owned(self) -> ValueAndSpan<'static> {
+        ValueAndSpan {
+            token: self.token.into_owned(),
+            span: self.span,
+            value: Cow::Owned(self.value.into_owned()),
+        }
+    }
 }
 
-fn match_filter(
-    vec: &mut Vec<FilterBlock>,
-    token: Token,
-    span: std::ops::Range<usize>,
-    block: &mut FilterBlock,
-) {
-    if let Some(key) = token.keyword_type() {
-        match key {
-            KeywordType::Block => {
-                new_block(vec, token, span.clone(), block);
-            }
-            KeywordType::Conditions => add_keyword(token, span, block),
-            KeywordType::Actions => add_keyword(token, span, block),
-            KeywordType::Operations => {}
-            KeywordType::Values(s) => {
-                add_values(token, span.clone(), block, s);
+/// `$key->value` tags from the comment on a block header, as in
+/// `Show # $type->expl->rare $tier->t1`.
+#[derive(PartialEq, Debug, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct BlockTags<'src> {
+    pub tags: BTreeMap<Cow<'src, str>, BlockTag<'src>>,
+}
+#[derive(PartialEq, Debug, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct BlockTag<'src> {
+    pub value: Cow<'src, str>,
+    pub span: Option<std::ops::Range<usize>>,
+}
+impl<'src> BlockTags<'src> {
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.tags.get(key).map(|t| t.value.as_ref())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tags.is_empty()
+    }
+
+    /// Reads the tags out of a comment starting at `offset` in the source.
+    pub fn parse(comment: &'src str, offset: usize) -> Self {
+        let mut tags = BlockTags::default();
+        let is_separator = |c: char| c.is_whitespace() || c == '#';
+        let mut start = None;
+        let end = std::iter::once((comment.len(), ' '));
+        for (i, c) in comment.char_indices().chain(end) {
+            match (is_separator(c), start) {
+                (false, None) => start = Some(i),
+                (true, Some(word_start)) => {
+                    start = None;
+                    let word = &comment[word_start..i];
+                    if let Some((key, value)) =
+                        word.strip_prefix('$').and_then(|w| w.split_once("->"))
+                    {
+                        let tag = BlockTag {
+                            value: Cow::Borrowed(value),
+                            span: Some(offset + word_start..offset + i),
+                        };
+                        tags.tags.insert(Cow::Borrowed(key), tag);
+                    }
+                }
+                _ => {}
             }
         }
+        tags
+    }
+
+    pub fn into_owned(self) -> BlockTags<'static> {
+        let tags = self.tags.into_iter().map(|(key, tag)| {
+            let tag = BlockTag {
+                value: Cow::Owned(tag.value.into_owned()),
+                span: tag.span,
+            };
+            (Cow::Owned(key.into_owned()), tag)
+        });
+        BlockTags {
+            tags: tags.collect(),
+        }
     }
 }
+impl BlockTag<'_> {
+    /// The `->` separated parts of the value, `["expl", "rare"]` for
+    /// `$type->expl->rare`.
+    pub fn path(&self) -> Vec<&str> {
+        self.value.split("->").collect()
+    }
+}
+
+#[derive(PartialEq, Debug, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ParseOutput<'src> {
+    pub blocks: Vec<FilterBlock<'src>>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+impl<'src> ParseOutput<'src> {
+    /// Copies every value out of the source, so that the output can outlive
+    /// it.
+    pub fn into_owned(self) -> ParseOutput<'static> {
+        ParseOutput {
+            blocks: self
+                .blocks
+                .into_iter()
+                .map(FilterBlock::into_owned)
+                .collect(),
+            diagnostics: self.diagnostics,
+        }
+    }
 
-pub fn parse(filter_file: &str) -> Vec<FilterBlock> {
-    let mut vec: Vec<FilterBlock> = vec![];
-    let mut block = FilterBlock::default();
-    let mut lex = Token::lexer(filter_file).spanned();
-    while let Some((token, span)) = lex.next() {
-        match_filter(&mut vec, token.clone(), span.clone(), &mut block);
+    pub fn has_errors(&self) -> bool {
+        self.diagnostics
+            .iter()
+            .any(|d| d.severity == Severity::Error)
     }
-    vec.push(block.clone());
-    vec
 }
 
-fn new_block(
-    vec: &mut Vec<FilterBlock>,
-    token: Token,
-    span: std::ops::Range<usize>,
-    block: &mut FilterBlock,
-) {
-    vec.push(block.clone());
-    block.clear();
-    block.block = Some(token.clone());
-    block.bspan = Some(span)
+#[derive(PartialEq, Debug, Default, Clone)]
+pub struct ParseOptions {
+    /// Flag keywords that the given game version does not know about.
+    pub target_version: Option<GameVersion>,
+    /// Read `Continue` as the start of a new block, as older versions of this
+    /// crate did, instead of as the end of the current one.
+    pub legacy_continue: bool,
 }
 
-fn add_keyword(token: Token, span: std::ops::Range<usize>, block: &mut FilterBlock) {
-    block.keywords.push(TokenAndSpan {
-        token: token.clone(),
-        span: Some(span),
-        ..Default::default()
-    })
+pub(crate) struct Parser<'a> {
+    source: &'a str,
+    options: ParseOptions,
+    output: ParseOutput<'a>,
+    block: FilterBlock<'a>,
+    // whether the current line is a block header, already has a keyword, or
+    // should be skipped
+    header_line: bool,
+    continue_line: bool,
+    import_line: bool,
+    line_keyword: bool,
+    skip_line: bool,
 }
 
-fn add_values(token: Token, span: std::ops::Range<usize>, block: &mut FilterBlock, string: String) {
-    if let Some(last_key) = block.keywords.last_mut() {
-        last_key.value.push(ValueAndSpan {
-            token: token.clone(),
+impl<'a> Parser<'a> {
+    pub(crate) fn new(source: &'a str, options: ParseOptions) -> Self {
+        Parser {
+            source,
+            options,
+            output: ParseOutput::default(),
+            block: FilterBlock::default(),
+            header_line: false,
+            continue_line: false,
+            import_line: false,
+            line_keyword: false,
+            skip_line: false,
+        }
+    }
+
+    pub(crate) fn match_filter(&mut self, token: Token<'a>, span: std::ops::Range<usize>) {
+        match token {
+            Token::Error => return self.lex_error(span),
+            Token::EndLine | Token::Hash => {
+                if token == Token::Hash && self.header_line {
+                    self.add_tags(span);
+                } else if token == Token::Hash && self.line_keyword && !self.skip_line {
+                    self.add_annotation(span);
+                }
+                if self.import_line {
+                    self.check_import();
+                }
+                self.header_line = false;
+                self.continue_line = false;
+                self.import_line = false;
+                self.line_keyword = false;
+                self.skip_line = false;
+                return;
+            }
+            _ => {}
+        }
+        if self.skip_line {
+            return;
+        }
+        if let Some(version) = self.options.target_version {
+            if let Some(message) = keywords::check(&token, version) {
+                self.error(message, span.clone());
+            }
+        }
+        if let Some(key) = token.keyword_type() {
+            match key {
+                KeywordType::Block => {
+                    self.new_block(token, span);
+                }
+                KeywordType::Flow if self.options.legacy_continue => {
+                    self.new_block(token, span);
+                }
+                KeywordType::Flow => self.add_continue(span),
+                KeywordType::Import => self.add_import(token, span),
+                KeywordType::Conditions | KeywordType::Actions => self.add_keyword(token, span),
+                KeywordType::Operations => self.add_operator(token, span),
+                KeywordType::Values(s) => {
+                    self.add_values(token, span, s);
+                }
+            }
+        }
+    }
+
+    pub(crate) fn finish(mut self) -> ParseOutput<'a> {
+        let block = self.end_input();
+        self.output.blocks.push(block);
+        self.output
+    }
+
+    /// Checks the last line and hands out the last block, for when the input
+    /// ends.
+    fn end_input(&mut self) -> FilterBlock<'a> {
+        if self.import_line {
+            self.check_import();
+            self.import_line = false;
+        }
+        std::mem::take(&mut self.block)
+    }
+
+    /// The oldest block that has been completed and not taken yet.
+    fn take_block(&mut self) -> Option<FilterBlock<'a>> {
+        if self.output.blocks.is_empty() {
+            None
+        } else {
+            Some(self.output.blocks.remove(0))
+        }
+    }
+
+    fn new_block(&mut self, token: Token<'a>, span: std::ops::Range<usize>) {
+        if self.import_line {
+            self.check_import();
+        }
+        self.output.blocks.push(std::mem::take(&mut self.block));
+        self.block = FilterBlock {
+            block: Some(token),
+            bspan: Some(span),
+            ..Default::default()
+        };
+        self.header_line = true;
+        self.continue_line = false;
+        self.import_line = false;
+        self.line_keyword = true;
+    }
+
+    fn add_tags(&mut self, span: std::ops::Range<usize>) {
+        self.block.tags = BlockTags::parse(&self.source[span.clone()], span.start);
+        self.block.comment = Some(self.comment(span)).filter(|c| !c.value.is_empty());
+    }
+
+    fn add_annotation(&mut self, span: std::ops::Range<usize>) {
+        let annotation = self.comment(span);
+        if let Some(keyword) = self.block.keywords.last_mut() {
+            keyword.annotation = Some(annotation);
+        }
+    }
+
+    /// The text of a comment, without the `#` and surrounding whitespace.
+    fn comment(&self, span: std::ops::Range<usize>) -> ValueAndSpan<'a> {
+        let comment = &self.source[span.clone()];
+        let text = comment.trim_start_matches('#').trim();
+        let skipped = comment.len() - comment.trim_start_matches('#').trim_start().len();
+        ValueAndSpan {
+            token: Token::Hash,
+            span: Some(span.start + skipped..span.start + skipped + text.len()),
+            value: Cow::Borrowed(text),
+        }
+    }
+
+    /// Whether keywords can go in the current block.
+    fn in_block(&self) -> bool {
+        !matches!(self.block.block, None | Some(Token::Import))
+    }
+
+    fn add_import(&mut self, token: Token<'a>, span: std::ops::Range<usize>) {
+        self.new_block(token, span);
+        self.block.import = Some(Import::default());
+        self.line_keyword = false;
+        self.import_line = true;
+    }
+
+    fn add_import_value(&mut self, token: Token<'a>, span: std::ops::Range<usize>) {
+        let import = match self.block.import.as_mut() {
+            Some(import) => import,
+            None => return,
+        };
+        match token {
+            Token::Quotes(path) if import.path_span.is_none() => {
+                import.path = match path {
+                    Cow::Borrowed(path) => Cow::Borrowed(path.trim_matches('"')),
+                    Cow::Owned(path) => Cow::Owned(path.trim_matches('"').to_string()),
+                };
+                import.path_span = Some(span);
+            }
+            Token::Text(ref text) if text == "Optional" && import.path_span.is_some() => {
+                import.optional = true;
+            }
+            _ => self.error(
+                "`Import` takes a quoted path and an optional `Optional`".to_string(),
+                span,
+            ),
+        }
+    }
+
+    fn check_import(&mut self) {
+        if self
+            .block
+            .import
+            .as_ref()
+            .is_some_and(|i| i.path_span.is_none())
+        {
+            let span = self.block.bspan.clone().unwrap_or_default();
+            self.error("`Import` needs a quoted path".to_string(), span);
+        }
+    }
+
+    fn add_continue(&mut self, span: std::ops::Range<usize>) {
+        if !self.in_block() {
+            self.error(
+                "`Continue` outside of a Show or Hide block".to_string(),
+                span.clone(),
+            );
+        }
+        self.block.continues = true;
+        self.block.continue_span = Some(span);
+        self.line_keyword = false;
+        self.continue_line = true;
+    }
+
+    fn add_keyword(&mut self, token: Token<'a>, span: std::ops::Range<usize>) {
+        if !self.in_block() {
+            self.error(
+                format!("`{:?}` outside of a Show or Hide block", token),
+                span.clone(),
+            );
+        }
+        self.block.keywords.push(TokenAndSpan {
+            token,
             span: Some(span),
-            value: string,
+            ..Default::default()
         });
-        // println!("STRING PLEASE: {:#?}", last_key.value);
-    };
+        self.line_keyword = true;
+    }
+
+    fn add_operator(&mut self, token: Token<'a>, span: std::ops::Range<usize>) {
+        match self.current_keyword() {
+            Some(last_key) => {
+                last_key.operator = Some(ValueAndSpan {
+                    value: Cow::Borrowed(token.operator_symbol().unwrap_or_default()),
+                    token,
+                    span: Some(span),
+                });
+            }
+            None => self.error("operator with no preceding keyword".to_string(), span),
+        }
+    }
+
+    fn add_values(&mut self, token: Token<'a>, span: std::ops::Range<usize>, string: Cow<'a, str>) {
+        if self.continue_line {
+            return self.error("`Continue` takes no values".to_string(), span);
+        }
+        if self.import_line {
+            return self.add_import_value(token, span);
+        }
+        if !self.line_keyword {
+            if let Token::Text(_) = token {
+                self.error(format!("unknown keyword `{}`", string), span);
+                self.skip_line = true;
+                return;
+            }
+        }
+        match self.current_keyword() {
+            Some(last_key) => last_key.value.push(ValueAndSpan {
+                token,
+                span: Some(span),
+                value: string,
+            }),
+            None => self.error("value with no preceding keyword".to_string(), span),
+        }
+    }
+
+    /// The keyword on the current line, if there is one.
+    fn current_keyword(&mut self) -> Option<&mut TokenAndSpan<'a>> {
+        if self.line_keyword {
+            self.block.keywords.last_mut()
+        } else {
+            None
+        }
+    }
+
+    fn lex_error(&mut self, mut span: std::ops::Range<usize>) {
+        // an unterminated string runs into the `\r` of a `\r\n`, which is part
+        // of the line ending, not of the string
+        if span.len() > 1 && self.source[span.clone()].ends_with('\r') {
+            span.end -= 1;
+        }
+        let text = &self.source[span.clone()];
+        if text.starts_with('"') {
+            self.error("unterminated string".to_string(), span);
+        } else {
+            self.error(format!("unexpected `{}`", text), span);
+        }
+    }
+
+    fn error(&mut self, message: String, span: std::ops::Range<usize>) {
+        let diagnostic = Diagnostic::new(self.source, Severity::Error, message, span);
+        self.output.diagnostics.push(diagnostic);
+    }
+}
+
+/// Parses a filter one block at a time. Only the block being read is kept,
+/// so large filters can be processed without holding all of their blocks,
+/// and stopping early skips lexing the rest of the source.
+///
+/// The first block holds whatever comes before the first `Show` or `Hide`.
+/// Diagnostics collect in the iterator until taken with `take_diagnostics`.
+pub struct BlockIter<'src> {
+    lexer: SpannedIter<'src, Token<'src>>,
+    parser: Parser<'src>,
+    done: bool,
+}
+
+impl<'src> BlockIter<'src> {
+    pub fn new(source: &'src str) -> Self {
+        Self::with_options(source, ParseOptions::default())
+    }
+
+    pub fn with_options(source: &'src str, options: ParseOptions) -> Self {
+        BlockIter {
+            lexer: Token::lexer(source).spanned(),
+            parser: Parser::new(source, options),
+            done: false,
+        }
+    }
+
+    /// Diagnostics for the source read so far.
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.parser.output.diagnostics
+    }
+
+    pub fn take_diagnostics(&mut self) -> Vec<Diagnostic> {
+        std::mem::take(&mut self.parser.output.diagnostics)
+    }
 }
 
-pub fn ignore_comments(lex: &mut Lexer<Token>) {
-    if lex.slice() == "#" {
+impl<'src> Iterator for BlockIter<'src> {
+    type Item = FilterBlock<'src>;
+
+    fn next(&mut self) -> Option<FilterBlock<'src>> {
         loop {
-            // let result = lex.next();
-            match lex.next() {
-                Some(Token::EndLine) => break,
-                _ => {}
+            if let Some(block) = self.parser.take_block() {
+                return Some(block);
+            }
+            if self.done {
+                return None;
+            }
+            match self.lexer.next() {
+                Some((token, span)) => self.parser.match_filter(token, span),
+                None => {
+                    self.done = true;
+                    return Some(self.parser.end_input());
+                }
             }
         }
     }
 }
+
+/// JSON Schema of a `ParseOutput` serialized with serde. A copy is kept in
+/// `filter_lib/schema/parse_output.json`.
+#[cfg(feature = "schema")]
+pub fn json_schema() -> schemars::schema::RootSchema {
+    schemars::schema_for!(ParseOutput<'static>)
+}
+
+pub fn parse(filter_file: &str) -> ParseOutput<'_> {
+    parse_with(filter_file, ParseOptions::default())
+}
+
+pub fn parse_with(filter_file: &str, options: ParseOptions) -> ParseOutput<'_> {
+    let mut iter = BlockIter::with_options(filter_file, options);
+    let blocks = iter.by_ref().collect();
+    ParseOutput {
+        blocks,
+        diagnostics: iter.take_diagnostics(),
+    }
+}
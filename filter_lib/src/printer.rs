@@ -0,0 +1,111 @@
+use crate::format::COMMENT_COLUMN;
+use crate::mode_parsing::{FilterBlock, Token, TokenAndSpan, ValueAndSpan};
+use std::fmt;
+
+/// Writes blocks back out as filter text that `mode_parsing::parse` reads
+/// back to the same blocks, spans aside.
+pub fn print(blocks: &[FilterBlock]) -> String {
+    let mut out = String::new();
+    for block in blocks.iter() {
+        if is_empty(block) {
+            continue;
+        }
+        if !out.is_empty() {
+            out.push('\n');
+        }
+        out.push_str(&block.to_string());
+    }
+    out
+}
+
+fn is_empty(block: &FilterBlock) -> bool {
+    block.block.is_none() && block.keywords.is_empty()
+}
+
+/// The keyword as written in a filter. Keyword variants of `Token` are named
+/// after the keyword itself.
+pub fn keyword_text(token: &Token) -> String {
+    match token.operator_symbol() {
+        Some(symbol) => symbol.to_string(),
+        None => format!("{:?}", token),
+    }
+}
+
+/// A value as it should be written, quoting it if it would not lex as a
+/// single value otherwise.
+pub fn value_text(value: &ValueAndSpan) -> String {
+    let s = value.value.as_ref();
+    if let Token::Quotes(_) = value.token {
+        s.to_string()
+    } else if s.is_empty() || s.contains(char::is_whitespace) || s.contains('#') {
+        format!("\"{}\"", s.trim_matches('"'))
+    } else {
+        s.to_string()
+    }
+}
+
+impl fmt::Display for TokenAndSpan<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&keyword_text(&self.token))?;
+        if let Some(operator) = &self.operator {
+            write!(f, " {}", keyword_text(&operator.token))?;
+        }
+        for value in self.value.iter() {
+            write!(f, " {}", value_text(value))?;
+        }
+        Ok(())
+    }
+}
+
+impl FilterBlock<'_> {
+    /// The header comment, or for blocks built without one, their tags.
+    fn write_comment(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(comment) = &self.comment {
+            write!(f, " # {}", comment.value)
+        } else if !self.tags.is_empty() {
+            f.write_str(" #")?;
+            for (key, tag) in self.tags.tags.iter() {
+                write!(f, " ${}->{}", key, tag.value)?;
+            }
+            Ok(())
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl fmt::Display for FilterBlock<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(import) = &self.import {
+            write!(f, "Import \"{}\"", import.path)?;
+            if import.optional {
+                f.write_str(" Optional")?;
+            }
+            self.write_comment(f)?;
+            return writeln!(f);
+        }
+        if let Some(header) = &self.block {
+            f.write_str(&keyword_text(header))?;
+            self.write_comment(f)?;
+            writeln!(f)?;
+        }
+        // keywords before the first header are not in any block, and are
+        // written unindented as they would be read
+        let indent = if self.block.is_some() { "\t" } else { "" };
+        for keyword in self.keywords.iter() {
+            let line = keyword.to_string();
+            match &keyword.annotation {
+                Some(annotation) => {
+                    let pad = COMMENT_COLUMN.saturating_sub(line.chars().count()).max(1);
+                    let padding = " ".repeat(pad);
+                    writeln!(f, "{}{}{}# {}", indent, line, padding, annotation.value)?
+                }
+                None => writeln!(f, "{}{}", indent, line)?,
+            }
+        }
+        if self.continues {
+            writeln!(f, "\tContinue")?;
+        }
+        Ok(())
+    }
+}
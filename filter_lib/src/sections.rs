@@ -0,0 +1,177 @@
+use crate::cst::{parse_cst, Cst, CstLine};
+use std::ops::Range;
+
+/// A `[[0100]]` (level 1) or `[0101]` (level 2) section of a NeverSink style
+/// filter, introduced by a banner comment with a numeric ID:
+///
+/// ```text
+/// #------------------------------------
+/// #   [1202] Rare Item Permutations
+/// #------------------------------------
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Section {
+    pub id: String,
+    pub title: String,
+    pub level: usize,
+    /// From the banner to the start of the next section of the same or a
+    /// higher level.
+    pub span: Range<usize>,
+    pub children: Vec<Section>,
+    /// Indices into the blocks returned by `mode_parsing::parse` of the
+    /// blocks directly in this section.
+    pub blocks: Vec<usize>,
+}
+
+/// An entry of the table of contents at the top of the filter.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TocEntry {
+    pub id: String,
+    pub title: String,
+    pub level: usize,
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct SectionTree {
+    pub toc: Vec<TocEntry>,
+    pub sections: Vec<Section>,
+}
+
+impl Section {
+    fn find(&self, id: &str) -> Option<&Section> {
+        if self.id == id {
+            return Some(self);
+        }
+        self.children.iter().find_map(|c| c.find(id))
+    }
+
+    fn section_of(&self, block: usize) -> Option<&Section> {
+        self.children
+            .iter()
+            .find_map(|c| c.section_of(block))
+            .or_else(|| Some(self).filter(|s| s.blocks.contains(&block)))
+    }
+
+    /// Blocks of this section and all of its children.
+    pub fn all_blocks(&self) -> Vec<usize> {
+        let mut blocks = self.blocks.clone();
+        for child in self.children.iter() {
+            blocks.extend(child.all_blocks());
+        }
+        blocks.sort_unstable();
+        blocks
+    }
+}
+
+impl SectionTree {
+    /// Looks a section up by ID, with or without its brackets.
+    pub fn find(&self, id: &str) -> Option<&Section> {
+        let id = id.trim_matches(|c| c == '[' || c == ']');
+        self.sections.iter().find_map(|s| s.find(id))
+    }
+
+    /// The innermost section containing the block at `block`.
+    pub fn section_of(&self, block: usize) -> Option<&Section> {
+        self.sections.iter().find_map(|s| s.section_of(block))
+    }
+
+    /// The source text of the section with the given ID.
+    pub fn extract<'a>(&self, source: &'a str, id: &str) -> Option<&'a str> {
+        self.find(id).and_then(|s| source.get(s.span.clone()))
+    }
+}
+
+pub fn parse_sections(source: &str) -> SectionTree {
+    section_tree(&parse_cst(source))
+}
+
+pub fn section_tree(cst: &Cst) -> SectionTree {
+    let mut tree = SectionTree::default();
+    let mut stack: Vec<Section> = vec![];
+    let mut offset = 0;
+    let mut rule_start: Option<usize> = None;
+    // where each block starts, split the way the parser splits them, which
+    // also starts blocks at a `Show` or `Hide` in the middle of a line
+    let blocks = cst.parse().blocks;
+    let mut headers = blocks
+        .iter()
+        .enumerate()
+        .filter_map(|(index, block)| Some((block.bspan.as_ref()?.start, index)))
+        .peekable();
+    for line in cst.lines() {
+        let len = line.to_string().len();
+        while let Some((_, index)) = headers.next_if(|(start, _)| *start < offset + len) {
+            if let Some(section) = stack.last_mut() {
+                section.blocks.push(index);
+            }
+        }
+        match line_comment(line).map(|c| (is_rule(c), section_title(c))) {
+            Some((true, _)) => {
+                rule_start = rule_start.or(Some(offset));
+            }
+            Some((false, Some((level, id, title)))) => {
+                if let Some(start) = rule_start {
+                    close_sections(&mut tree, &mut stack, level, start);
+                    stack.push(Section {
+                        id,
+                        title,
+                        level,
+                        span: start..start,
+                        children: vec![],
+                        blocks: vec![],
+                    });
+                } else {
+                    tree.toc.push(TocEntry { id, title, level });
+                }
+                rule_start = None;
+            }
+            _ => rule_start = None,
+        }
+        offset += len;
+    }
+    close_sections(&mut tree, &mut stack, 0, offset);
+    tree
+}
+
+/// Closes every open section at `level` or deeper, ending them at `end`.
+fn close_sections(tree: &mut SectionTree, stack: &mut Vec<Section>, level: usize, end: usize) {
+    while stack.last().is_some_and(|s| s.level >= level) {
+        let mut section = stack.pop().unwrap();
+        section.span.end = end;
+        match stack.last_mut() {
+            Some(parent) => parent.children.push(section),
+            None => tree.sections.push(section),
+        }
+    }
+}
+
+fn line_comment(line: &CstLine) -> Option<&str> {
+    if line.tokens.is_empty() {
+        line.comment()
+            .map(|c| c.text.trim_start_matches('#').trim())
+    } else {
+        None
+    }
+}
+
+fn is_rule(comment: &str) -> bool {
+    !comment.is_empty() && comment.chars().all(|c| c == '-' || c == '=')
+}
+
+fn section_title(comment: &str) -> Option<(usize, String, String)> {
+    let (level, close) = if comment.starts_with("[[") {
+        (1, "]]")
+    } else if comment.starts_with('[') {
+        (2, "]")
+    } else {
+        return None;
+    };
+    let rest = &comment[close.len()..];
+    let end = rest.find(close)?;
+    let id = &rest[..end];
+    if id.is_empty() || !id.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let title = rest[end + close.len()..].trim();
+    Some((level, id.to_string(), title.to_string()))
+}
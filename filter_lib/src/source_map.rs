@@ -0,0 +1,190 @@
+use crate::diagnostics::Diagnostic;
+use std::ops::Range;
+
+/// One of the files of a `SourceMap`.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct FileId(pub u32);
+
+/// A byte range in one file.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct FileSpan {
+    pub file: FileId,
+    pub span: Range<usize>,
+}
+
+/// A position as editors show it. `line` and `column` are 1-based, with
+/// columns counted in characters; `utf16_column` is 0-based and counted in
+/// UTF-16 code units, as the Language Server Protocol does.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct Location {
+    pub line: usize,
+    pub column: usize,
+    pub utf16_column: usize,
+}
+
+/// Start offsets of every line of a source, for converting between byte
+/// offsets and line/column positions without rescanning the text.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct LineIndex {
+    line_starts: Vec<usize>,
+}
+
+impl LineIndex {
+    pub fn new(source: &str) -> Self {
+        let newlines = source.match_indices('\n').map(|(i, _)| i + 1);
+        LineIndex {
+            line_starts: std::iter::once(0).chain(newlines).collect(),
+        }
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.line_starts.len()
+    }
+
+    /// Byte range of a 1-based line, without its newline.
+    pub fn line_span(&self, source: &str, line: usize) -> Option<Range<usize>> {
+        let start = *self.line_starts.get(line.checked_sub(1)?)?;
+        let end = self
+            .line_starts
+            .get(line)
+            .map_or(source.len(), |next| next - 1);
+        Some(start..end)
+    }
+
+    pub fn location(&self, source: &str, offset: usize) -> Location {
+        let offset = offset.min(source.len());
+        let line = self.line_starts.partition_point(|&start| start <= offset);
+        let before = &source[self.line_starts[line - 1]..offset];
+        Location {
+            line,
+            column: before.chars().count() + 1,
+            utf16_column: before.encode_utf16().count(),
+        }
+    }
+
+    /// The byte offset of a 1-based line and column, if the line has that
+    /// many characters.
+    pub fn offset(&self, source: &str, line: usize, column: usize) -> Option<usize> {
+        let span = self.line_span(source, line)?;
+        let text = &source[span.clone()];
+        let column = column.checked_sub(1)?;
+        text.char_indices()
+            .map(|(i, _)| i)
+            .chain(std::iter::once(text.len()))
+            .nth(column)
+            .map(|i| span.start + i)
+    }
+
+    /// The byte offset of a 0-based UTF-16 column on a 1-based line.
+    pub fn offset_utf16(&self, source: &str, line: usize, utf16_column: usize) -> Option<usize> {
+        let span = self.line_span(source, line)?;
+        let mut units = 0;
+        for (i, c) in source[span.clone()].char_indices() {
+            if units == utf16_column {
+                return Some(span.start + i);
+            }
+            units += c.len_utf16();
+        }
+        Some(span.end).filter(|_| units == utf16_column)
+    }
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct SourceFile {
+    pub name: String,
+    pub source: String,
+    /// Where the file starts in `SourceMap::combined`.
+    pub start: usize,
+    pub lines: LineIndex,
+}
+
+/// The files of a filter that is built from several, in the order they are
+/// combined. Spans into the combined text map back to a file and a span in
+/// it.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct SourceMap {
+    pub files: Vec<SourceFile>,
+}
+
+impl SourceMap {
+    pub fn add(&mut self, name: &str, source: &str) -> FileId {
+        let start = self
+            .files
+            .last()
+            .map_or(0, |f| f.start + f.source.len() + separator(&f.source).len());
+        self.files.push(SourceFile {
+            name: name.to_string(),
+            source: source.to_string(),
+            start,
+            lines: LineIndex::new(source),
+        });
+        FileId(self.files.len() as u32 - 1)
+    }
+
+    pub fn file(&self, id: FileId) -> &SourceFile {
+        &self.files[id.0 as usize]
+    }
+
+    /// Every file one after the other, the text to parse for the whole filter.
+    /// A file that does not end with a newline gets one, so that its last
+    /// line does not run into the next file.
+    pub fn combined(&self) -> String {
+        let mut combined = String::new();
+        for (i, file) in self.files.iter().enumerate() {
+            combined.push_str(&file.source);
+            if i + 1 < self.files.len() {
+                combined.push_str(separator(&file.source));
+            }
+        }
+        combined
+    }
+
+    /// The file a span of the combined text starts in, and the span within
+    /// that file.
+    pub fn lookup(&self, span: Range<usize>) -> FileSpan {
+        let index = self
+            .files
+            .partition_point(|f| f.start <= span.start)
+            .saturating_sub(1);
+        let file = &self.files[index];
+        FileSpan {
+            file: FileId(index as u32),
+            span: span.start - file.start..span.end - file.start,
+        }
+    }
+
+    pub fn location(&self, span: &FileSpan) -> Location {
+        let file = self.file(span.file);
+        file.lines.location(&file.source, span.span.start)
+    }
+
+    /// Moves a diagnostic on the combined text to the file it points into.
+    pub fn relocate(&self, diagnostic: Diagnostic) -> Diagnostic {
+        let FileSpan { file, span } = self.lookup(diagnostic.span.clone());
+        let source = &self.file(file).source;
+        Diagnostic {
+            file: Some(file),
+            ..Diagnostic::new(source, diagnostic.severity, diagnostic.message, span)
+        }
+    }
+
+    /// `name:line:column: severity: message` for a diagnostic that has been
+    /// relocated.
+    pub fn display(&self, diagnostic: &Diagnostic) -> String {
+        match diagnostic.file {
+            Some(file) => format!("{}:{}", self.file(file).name, diagnostic),
+            None => diagnostic.to_string(),
+        }
+    }
+}
+
+/// What goes between a file and the next in `SourceMap::combined`.
+fn separator(source: &str) -> &'static str {
+    if source.ends_with('\n') {
+        ""
+    } else {
+        "\n"
+    }
+}
@@ -0,0 +1,102 @@
+use crate::ast::{Action, AlertSound, EffectColor, IconShape, IconSize, Rgba};
+use crate::evaluate::evaluate;
+use crate::item::Item;
+use crate::mode_parsing::FilterBlock;
+use std::ops::Range;
+
+/// A style property and the `bspan` of the block that set it, which is
+/// `None` only for the visibility of items no block matches.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Sourced<T> {
+    pub value: T,
+    pub bspan: Option<Range<usize>>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Sound {
+    Alert(AlertSound),
+    Custom { path: String, volume: Option<u16> },
+}
+
+/// `size` is `IconSize::Disabled`, and `color` and `shape` are `None`, when
+/// a block removed the icon.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct MinimapIcon {
+    pub size: IconSize,
+    pub color: Option<EffectColor>,
+    pub shape: Option<IconShape>,
+}
+
+/// `color` is `None` when a block removed the beam.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Beam {
+    pub color: Option<EffectColor>,
+    pub temp: bool,
+}
+
+/// How an item looks after every block that matched it. A property no
+/// block set is `None` and keeps the game's default.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ResolvedStyle {
+    pub visible: Sourced<bool>,
+    pub font_size: Option<Sourced<u32>>,
+    pub text_color: Option<Sourced<Rgba>>,
+    pub border_color: Option<Sourced<Rgba>>,
+    pub background_color: Option<Sourced<Rgba>>,
+    pub sound: Option<Sourced<Sound>>,
+    /// Whether the item makes its usual drop sound.
+    pub drop_sound: Option<Sourced<bool>>,
+    pub minimap_icon: Option<Sourced<MinimapIcon>>,
+    pub beam: Option<Sourced<Beam>>,
+}
+
+/// The style of `item`: the actions of every block that matched it, from the
+/// first to the one that decided, with later blocks overriding what earlier
+/// `Continue` blocks set.
+pub fn resolve_style(blocks: &[FilterBlock], item: &Item) -> ResolvedStyle {
+    let verdict = evaluate(blocks, item);
+    let mut style = ResolvedStyle {
+        visible: Sourced {
+            value: verdict.visible,
+            bspan: verdict.block.and_then(|i| blocks[i].bspan.clone()),
+        },
+        font_size: None,
+        text_color: None,
+        border_color: None,
+        background_color: None,
+        sound: None,
+        drop_sound: None,
+        minimap_icon: None,
+        beam: None,
+    };
+    for block in verdict.matched.iter().map(|&i| &blocks[i]) {
+        for action in block.actions() {
+            match action {
+                Action::SetFontSize(size) => style.font_size = sourced(block, size),
+                Action::SetTextColor(color) => style.text_color = sourced(block, color),
+                Action::SetBorderColor(color) => style.border_color = sourced(block, color),
+                Action::SetBackgroundColor(color) => style.background_color = sourced(block, color),
+                Action::PlayAlertSound(sound) => style.sound = sourced(block, Sound::Alert(sound)),
+                Action::CustomAlertSound { path, volume } => {
+                    style.sound = sourced(block, Sound::Custom { path, volume })
+                }
+                Action::DisableDropSound(disable) => style.drop_sound = sourced(block, !disable),
+                Action::EnableDropSound(enable) => style.drop_sound = sourced(block, enable),
+                Action::MinimapIcon { size, color, shape } => {
+                    style.minimap_icon = sourced(block, MinimapIcon { size, color, shape })
+                }
+                Action::PlayEffect { color, temp } => {
+                    style.beam = sourced(block, Beam { color, temp })
+                }
+            }
+        }
+    }
+    style
+}
+
+fn sourced<T>(block: &FilterBlock, value: T) -> Option<Sourced<T>> {
+    Some(Sourced {
+        value,
+        bspan: block.bspan.clone(),
+    })
+}
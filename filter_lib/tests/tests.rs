@@ -1,11 +1,43 @@
 #[cfg(test)]
 mod tests {
     // use filter_lib::logos_parsing;
-    use filter_lib::mode_parsing;
+    use filter_lib::ast::{
+        self, Action, AlertSound, Condition, EffectColor, IconShape, IconSize, Influence, Op,
+        Rarity, Rgba, SoundId,
+    };
+    use filter_lib::color::Hsl;
+    use filter_lib::cst;
+    use filter_lib::encoding::{self, Encoding, LineEnding, TextFormat};
+    use filter_lib::evaluate::evaluate;
+    use filter_lib::explain::explain;
+    use filter_lib::format;
+    use filter_lib::imports;
+    use filter_lib::incremental::{self, TextEdit};
+    use filter_lib::item::Item;
+    use filter_lib::keywords::{self, GameVersion};
+    use filter_lib::mode_parsing::{self, ParseOptions};
+    use filter_lib::printer;
+    use filter_lib::sections;
+    use filter_lib::source_map::{FileId, LineIndex, SourceMap};
+    use filter_lib::style::{resolve_style, Sound};
+    use proptest::prelude::*;
+    use std::borrow::Cow;
+    use std::collections::HashMap;
+    use std::path::{Path, PathBuf};
+    /// small.filter starts with a `Continue` header, as filters written for
+    /// the old meaning of `Continue` do.
+    fn parse_legacy(source: &str) -> mode_parsing::ParseOutput<'_> {
+        let options = ParseOptions {
+            legacy_continue: true,
+            ..Default::default()
+        };
+        mode_parsing::parse_with(source, options)
+    }
+
     #[test]
     fn test_new_filter_block() {
         let filter_file = include_str!("../src/test_filters/small.filter");
-        let x = mode_parsing::parse(filter_file);
+        let x = mode_parsing::parse(filter_file).blocks;
         // for b in x.iter(){
         //     for k in b.keywords.iter(){
         //  println!("{:#?}", k.value);
@@ -14,7 +46,1113 @@ mod tests {
         // }
         println!("{:#?}", x);
         // println!("{:#?}", x.len());
+    }
+
+    #[test]
+    fn test_typed_statements() {
+        let filter_file = include_str!("../src/test_filters/small.filter");
+        let blocks = parse_legacy(filter_file).blocks;
+        let hide = &blocks[2];
+        let conditions = hide.conditions();
+        assert!(conditions.contains(&Condition::Identified(true)));
+        assert!(conditions.contains(&Condition::Rarity(Op::Eq, vec![Rarity::Rare])));
+        assert!(conditions.contains(&Condition::Class {
+            exact: false,
+            names: vec!["Bows".to_string(), "Wands".to_string()],
+        }));
+        let actions = hide.actions();
+        assert!(actions.contains(&Action::SetBorderColor(Rgba {
+            r: 0,
+            g: 240,
+            b: 190,
+            a: 240
+        })));
+        assert!(actions.contains(&Action::PlayAlertSound(AlertSound {
+            id: SoundId::Number(3),
+            volume: 300,
+            positional: false,
+        })));
+        assert_eq!(hide.keywords.len(), conditions.len() + actions.len());
+    }
+
+    #[test]
+    fn test_operators() {
+        let blocks = mode_parsing::parse(
+            "Show\n\tDropLevel > 50\n\tRarity <= Rare\n\tBaseType == \"Vaal Regalia\"\n\tClass \"Rings\"\n",
+        )
+        .blocks;
+        let keywords = &blocks[1].keywords;
+        assert_eq!(keywords[0].operator.as_ref().unwrap().value, ">");
+        assert_eq!(keywords[3].operator, None);
+        let conditions = blocks[1].conditions();
+        assert_eq!(conditions[0], Condition::DropLevel(Op::Gt, 50));
+        assert_eq!(conditions[1], Condition::Rarity(Op::Le, vec![Rarity::Rare]));
+        assert_eq!(
+            conditions[2],
+            Condition::BaseType {
+                exact: true,
+                names: vec!["Vaal Regalia".to_string()],
+            }
+        );
+        assert_eq!(
+            conditions[3],
+            Condition::Class {
+                exact: false,
+                names: vec!["Rings".to_string()],
+            }
+        );
+    }
+
+    #[test]
+    fn test_diagnostics() {
+        let output =
+            mode_parsing::parse("Show\n\tHarvestSeed True\n\tClass \"Bows\n\tRarity Rare\n");
+        let messages: Vec<String> = output.diagnostics.iter().map(|d| d.to_string()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "2:2: error: unknown keyword `HarvestSeed`",
+                "3:8: error: unterminated string",
+            ]
+        );
+        assert!(output.has_errors());
+        assert_eq!(output.blocks[1].keywords.len(), 2);
+
+        let output = mode_parsing::parse("Show\n\t50\n");
+        assert_eq!(
+            output.diagnostics[0].message,
+            "value with no preceding keyword"
+        );
+
+        let output = mode_parsing::parse("Show\n\tClass \"Bows\" ~\n");
+        assert_eq!(output.diagnostics[0].message, "unexpected `~`");
+    }
+
+    #[test]
+    fn test_cst_round_trip() {
+        for filter_file in [
+            include_str!("../src/test_filters/small.filter"),
+            include_str!("../src/test_filters/filter.filter"),
+            "Show # no newline at the end",
+            "  \n\tClass \"a # b\"  # comment\n",
+        ]
+        .iter()
+        {
+            let tree = cst::parse_cst(filter_file);
+            assert_eq!(&tree.to_string(), filter_file);
+        }
+
+        let filter_file = include_str!("../src/test_filters/small.filter");
+        let tree = cst::parse_cst(filter_file);
+        assert_eq!(tree.parse(), mode_parsing::parse(filter_file));
+        let line = &tree.blocks[2].lines[9];
+        assert_eq!(
+            line.keyword().unwrap().token,
+            mode_parsing::Token::SetTextColor
+        );
+        assert_eq!(line.comment().unwrap().text, "# TEXTCOLOR:\t Rare 75+");
+    }
+
+    fn without_spans(mut blocks: Vec<mode_parsing::FilterBlock>) -> Vec<mode_parsing::FilterBlock> {
+        for block in blocks.iter_mut() {
+            block.bspan = None;
+            block.continue_span = None;
+            block.tags.tags.values_mut().for_each(|t| t.span = None);
+            block.comment.iter_mut().for_each(|c| c.span = None);
+            for keyword in block.keywords.iter_mut() {
+                keyword.span = None;
+                keyword.operator.iter_mut().for_each(|o| o.span = None);
+                keyword.value.iter_mut().for_each(|v| v.span = None);
+                keyword.annotation.iter_mut().for_each(|a| a.span = None);
+            }
+        }
+        blocks
+    }
+
+    #[test]
+    fn test_printer_round_trip() {
+        let legacy = ParseOptions {
+            legacy_continue: true,
+            ..Default::default()
+        };
+        for (filter_file, options) in [
+            (
+                include_str!("../src/test_filters/small.filter"),
+                legacy.clone(),
+            ),
+            (
+                include_str!("../src/test_filters/filter.filter"),
+                legacy.clone(),
+            ),
+            (
+                include_str!("../src/test_filters/filter.filter"),
+                ParseOptions::default(),
+            ),
+            (
+                "Show\n\tClass \"Rings\"\n\tContinue\nHide\n",
+                ParseOptions::default(),
+            ),
+        ]
+        .iter()
+        {
+            let blocks = mode_parsing::parse_with(filter_file, options.clone()).blocks;
+            let printed = printer::print(&blocks);
+            let reparsed = mode_parsing::parse_with(&printed, options.clone());
+            assert!(reparsed.diagnostics.is_empty());
+            assert_eq!(without_spans(reparsed.blocks), without_spans(blocks));
+        }
+
+        let blocks =
+            mode_parsing::parse("Show\n\tDropLevel > 50\n\tBaseType == \"Vaal Regalia\"\n").blocks;
+        assert_eq!(
+            printer::print(&blocks),
+            "Show\n\tDropLevel > 50\n\tBaseType == \"Vaal Regalia\"\n"
+        );
+
+        let source = "Class \"Rings\"\nShow # Rare rings $tier->t1\n\tRarity Rare\n";
+        let output = mode_parsing::parse(source);
+        let header = output.blocks[1].comment.as_ref().unwrap();
+        assert_eq!(header.value, "Rare rings $tier->t1");
+        assert_eq!(header.span, Some(21..41));
+        let printed = printer::print(&output.blocks);
+        assert_eq!(
+            printed,
+            "Class \"Rings\"\n\nShow # Rare rings $tier->t1\n\tRarity Rare\n"
+        );
+        let reparsed = mode_parsing::parse(&printed);
+        assert_eq!(reparsed.diagnostics.len(), output.diagnostics.len());
+        assert_eq!(without_spans(reparsed.blocks), without_spans(output.blocks));
+    }
+
+    #[test]
+    fn test_format() {
+        let source = "Show # $tier->t1  \n    SetFontSize 45 # big\n  Class Rings \"Amulets\"\n# note\n\tSocketGroup 5RRG\n    Continue\n\n";
+        assert_eq!(
+            format::format(source),
+            "Show # $tier->t1\n\tClass \"Rings\" \"Amulets\"\n# note\n\tSocketGroup 5RRG\n\tSetFontSize 45                          # big\n\tContinue\n\n"
+        );
+
+        for source in ["Show\n\tClass Two-Hand\n", "Show\n\tBaseType Maelström\n"] {
+            assert_eq!(format::format(source), source);
+        }
+        let formatted = format::format("Show\r\nContinue-1300BaseType\r\nSockets");
+        assert_eq!(formatted, "Show\r\n\tSockets\r\nContinue-1300BaseType\r\n");
+        assert!(format::is_formatted(&formatted));
+        assert_eq!(
+            format::format("Show\n  Class  Two-Hand\n  BaseType   Rings\n"),
+            "Show\n  Class  Two-Hand\n\tBaseType \"Rings\"\n"
+        );
+
+        let small = include_str!("../src/test_filters/small.filter");
+        assert!(format::is_formatted(small));
+        let formatted = format::format(include_str!("../src/test_filters/filter.filter"));
+        assert!(format::is_formatted(&formatted));
+    }
+
+    #[test]
+    fn test_sections() {
+        let filter_file = include_str!("../src/test_filters/filter.filter");
+        let tree = sections::parse_sections(filter_file);
+        let blocks = mode_parsing::parse(filter_file).blocks;
+
+        assert_eq!(tree.toc[0].id, "0100");
+        assert_eq!(tree.toc[3].level, 1);
+        assert_eq!(tree.toc[5].title, "Layer - T1 - ECONOMY");
+        assert_eq!(tree.toc[5].level, 2);
+
+        let section = tree.find("[1202]").unwrap();
+        assert_eq!(section.title, "Rare Item Permutations");
+        assert!(!section.blocks.is_empty());
+        for &index in section.blocks.iter() {
+            assert_eq!(tree.section_of(index).unwrap().id, "1202");
+            assert_eq!(blocks[index].block, Some(mode_parsing::Token::Show));
+        }
+        let parent = tree.find("[[1200]]").unwrap();
+        assert!(parent.children.iter().any(|c| c.id == "1202"));
+        assert!(parent.all_blocks().contains(&section.blocks[0]));
+
+        let text = tree.extract(filter_file, "1202").unwrap();
+        assert!(text.starts_with("#------------------------------------\n#   [1202]"));
+        assert!(text.ends_with("\n\n"));
+        assert_eq!(
+            mode_parsing::parse(text).blocks.len(),
+            section.blocks.len() + 1
+        );
+        assert!(tree.find("WELCOME").is_none());
+        assert!(tree.sections.iter().all(|s| s.id != "WELCOME"));
+
+        let source = "#---\n# [0100] First\n#---\nShow\n\tClass \"a\" Hide\n#---\n# [0200] Second\n#---\nShow\n";
+        let tree = sections::parse_sections(source);
+        assert_eq!(mode_parsing::parse(source).blocks.len(), 4);
+        assert_eq!(tree.find("0100").unwrap().blocks, [1, 2]);
+        assert_eq!(tree.find("0200").unwrap().blocks, [3]);
+    }
+
+    #[test]
+    fn test_block_tags() {
+        let filter_file = include_str!("../src/test_filters/small.filter");
+        let blocks = parse_legacy(filter_file).blocks;
+        assert_eq!(blocks[1].tags.get("type"), Some("expl->rare"));
+        let tag = &blocks[2].tags.tags["type"];
+        assert_eq!(tag.path(), vec!["expl", "rare"]);
+        assert_eq!(&filter_file[tag.span.clone().unwrap()], "$type->expl->rare");
+        assert_eq!(blocks[0].tags, Default::default());
+
+        let blocks = mode_parsing::parse(
+            "Show # %D0 %RECEIVER->CRAFTING->T2 $type->normalcraft->rest $tier->t2\n\tClass \"Rings\" # $tier->t9\n",
+        )
+        .blocks;
+        assert_eq!(blocks[1].tags.tags.len(), 2);
+        assert_eq!(blocks[1].tags.get("type"), Some("normalcraft->rest"));
+        assert_eq!(blocks[1].tags.get("tier"), Some("t2"));
+
+        let source = "Show #\u{a0}$tier->t1\u{3000}$type->x";
+        let blocks = mode_parsing::parse(source).blocks;
+        for (key, tag) in blocks[1].tags.tags.iter() {
+            let text = &source[tag.span.clone().unwrap()];
+            assert_eq!(text, format!("${}->{}", key, tag.value));
+        }
+        assert_eq!(blocks[1].tags.get("type"), Some("x"));
+
+        let filter_file = include_str!("../src/test_filters/filter.filter");
+        let blocks = mode_parsing::parse(filter_file).blocks;
+        let typed = blocks.iter().filter(|b| b.tags.get("type").is_some());
+        assert_eq!(typed.count(), 234);
+        assert!(blocks.iter().any(|b| b.tags.get("tier") == Some("t1")));
+    }
+
+    #[test]
+    fn test_annotations() {
+        let filter_file = include_str!("../src/test_filters/small.filter");
+        let blocks = parse_legacy(filter_file).blocks;
+        let keywords = &blocks[2].keywords;
+        let text_color = &keywords[8];
+        assert_eq!(text_color.token, mode_parsing::Token::SetTextColor);
+        let annotation = text_color.annotation.as_ref().unwrap();
+        assert_eq!(annotation.value, "TEXTCOLOR:\t Rare 75+");
+        assert_eq!(
+            &filter_file[annotation.span.clone().unwrap()],
+            annotation.value
+        );
+        assert_eq!(text_color.style_name(), Some(("TEXTCOLOR", "Rare 75+")));
+        assert_eq!(
+            keywords[9].style_name(),
+            Some(("BORDERCOLOR", "Special Base"))
+        );
+        assert_eq!(keywords[7].annotation, None);
+
+        let blocks = mode_parsing::parse("Show # $tier->t1\n\tClass \"a\" # note # more\n").blocks;
+        assert_eq!(
+            blocks[1].keywords[0].annotation.as_ref().unwrap().value,
+            "note # more"
+        );
+        assert_eq!(blocks[1].keywords[0].style_name(), None);
+    }
+
+    #[test]
+    fn test_continue() {
+        let source = "Show\n\tClass \"a\"\n\tContinue\nHide\n";
+        let output = mode_parsing::parse(source);
+        assert!(output.diagnostics.is_empty());
+        assert_eq!(output.blocks.len(), 3);
+        let show = &output.blocks[1];
+        assert!(show.continues);
+        assert_eq!(&source[show.continue_span.clone().unwrap()], "Continue");
+        assert_eq!(show.keywords.len(), 1);
+        assert!(!output.blocks[2].continues);
+
+        let blocks = parse_legacy(source).blocks;
+        assert_eq!(blocks.len(), 4);
+        assert_eq!(blocks[2].block, Some(mode_parsing::Token::Continue));
+        assert!(!blocks[1].continues);
+
+        let output = mode_parsing::parse("Continue\nShow\n\tContinue 1\n");
+        let messages: Vec<&str> = output
+            .diagnostics
+            .iter()
+            .map(|d| d.message.as_str())
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                "`Continue` outside of a Show or Hide block",
+                "`Continue` takes no values"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_colors() {
+        let blocks =
+            mode_parsing::parse("Show\n\tSetTextColor 255 190 0\n\tSetBorderColor 0 240 190 240\n")
+                .blocks;
+        let actions = blocks[1].actions();
+        assert_eq!(actions[0], Action::SetTextColor(Rgba::new(255, 190, 0)));
+        assert_eq!(
+            actions[1],
+            Action::SetBorderColor(Rgba::new(0, 240, 190).with_alpha(240))
+        );
+
+        let source = "Show\n\tSetTextColor 255 300 0\n\tSetTextColor 1 2\n";
+        let blocks = mode_parsing::parse(source).blocks;
+        let error = blocks[1].keywords[0].statement().unwrap_err();
+        assert_eq!(error.message, "color component `300` is not in 0-255");
+        assert_eq!(&source[error.span.unwrap()], "300");
+        let error = blocks[1].keywords[1].statement().unwrap_err();
+        assert_eq!(error.message, "expected 3 or 4 color components, found 2");
+
+        let color = Rgba::new(255, 190, 0);
+        assert_eq!(color.to_hex(), "#ffbe00");
+        assert_eq!(color.with_alpha(128).to_hex(), "#ffbe0080");
+        assert_eq!(Rgba::from_hex("#ffbe00"), Some(color));
+        assert_eq!(Rgba::from_hex("fff"), Some(Rgba::new(255, 255, 255)));
+        assert_eq!(Rgba::from_hex("#ffbe0"), None);
+        assert_eq!(color.with_alpha(240).to_string(), "255 190 0 240");
+
+        let hsl = Rgba::new(255, 0, 0).to_hsl();
+        assert_eq!(
+            hsl,
+            Hsl {
+                h: 0.0,
+                s: 1.0,
+                l: 0.5
+            }
+        );
+        assert_eq!(color.to_hsl().to_rgba(), color);
+        let white = Rgba::new(255, 255, 255).to_oklab();
+        assert!((white.l - 1.0).abs() < 1e-4 && white.a.abs() < 1e-4);
+        let black = Rgba::new(0, 0, 0).to_oklab();
+        assert!((white.distance(&black) - 1.0).abs() < 1e-4);
+
+        assert_eq!(Rgba::named("blue"), Rgba::named("Blue"));
+        assert!(Rgba::named("Magenta").is_none());
+    }
+
+    #[test]
+    fn test_icons_and_effects() {
+        let source = "Show\n\tMinimapIcon 0 Blue Diamond\n\tMinimapIcon -1\n\tPlayEffect White Temp\n\tPlayEffect None\n";
+        let output = mode_parsing::parse(source);
+        assert!(output.diagnostics.is_empty());
+        assert_eq!(
+            output.blocks[1].actions(),
+            vec![
+                Action::MinimapIcon {
+                    size: IconSize::Large,
+                    color: Some(EffectColor::Blue),
+                    shape: Some(IconShape::Diamond),
+                },
+                Action::MinimapIcon {
+                    size: IconSize::Disabled,
+                    color: None,
+                    shape: None,
+                },
+                Action::PlayEffect {
+                    color: Some(EffectColor::White),
+                    temp: true,
+                },
+                Action::PlayEffect {
+                    color: None,
+                    temp: false,
+                },
+            ]
+        );
+        assert_eq!(EffectColor::Blue.rgba(), Rgba::named("Blue").unwrap());
+
+        let source = "Show\n\tMinimapIcon 3 Blue Diamond\n\tMinimapIcon 1 Magenta Star\n\tMinimapIcon 1 Red\n\tMinimapIcon -1 Red Star\n\tPlayEffect None Temp\n\tPlayEffect Red Forever\n";
+        let output = mode_parsing::parse(source);
+        let messages: Vec<String> = ast::validate(source, &output.blocks)
+            .iter()
+            .map(|d| d.to_string())
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                "2:14: error: invalid `MinimapIcon` value `3`",
+                "3:16: error: invalid `MinimapIcon` value `Magenta`",
+                "4:2: error: `MinimapIcon` is missing value 3",
+                "5:17: error: `MinimapIcon -1` takes no color or shape",
+                "6:18: error: `PlayEffect None` can not be `Temp`",
+                "7:17: error: expected `Temp` after the `PlayEffect` color, found `Forever`",
+            ]
+        );
+
+        let filter_file = include_str!("../src/test_filters/filter.filter");
+        let output = mode_parsing::parse(filter_file);
+        assert!(ast::validate(filter_file, &output.blocks).is_empty());
+    }
+
+    #[test]
+    fn test_sounds() {
+        let source = "Show\n\tPlayAlertSound 6\n\tPlayAlertSoundPositional ShAlchemy 200\n\tCustomAlertSound \"sounds/drop.mp3\" 120\n\tDisableDropSound\n\tEnableDropSound False\n";
+        let output = mode_parsing::parse(source);
+        assert!(output.diagnostics.is_empty());
+        assert_eq!(
+            output.blocks[1].actions(),
+            vec![
+                Action::PlayAlertSound(AlertSound {
+                    id: SoundId::Number(6),
+                    volume: ast::DEFAULT_VOLUME,
+                    positional: false,
+                }),
+                Action::PlayAlertSound(AlertSound {
+                    id: SoundId::ShAlchemy,
+                    volume: 200,
+                    positional: true,
+                }),
+                Action::CustomAlertSound {
+                    path: "sounds/drop.mp3".to_string(),
+                    volume: Some(120),
+                },
+                Action::DisableDropSound(true),
+                Action::EnableDropSound(false),
+            ]
+        );
+
+        let source = "Show\n\tPlayAlertSound 3 301\n\tPlayAlertSound ShNope\n\tPlayAlertSound 17\n\tCustomAlertSound \"a.mp3\" 500\n";
+        let output = mode_parsing::parse(source);
+        let messages: Vec<String> = ast::validate(source, &output.blocks)
+            .iter()
+            .map(|d| d.to_string())
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                "2:19: error: volume `301` is not in 0-300",
+                "3:17: error: unknown alert sound `ShNope`",
+                "4:17: error: unknown alert sound `17`",
+                "5:27: error: volume `500` is not in 0-300",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_block_iter() {
+        let filter_file = include_str!("../src/test_filters/filter.filter");
+        let blocks: Vec<_> = mode_parsing::BlockIter::new(filter_file).collect();
+        assert_eq!(blocks, mode_parsing::parse(filter_file).blocks);
+
+        let mut iter =
+            mode_parsing::BlockIter::new("# header\nShow\n\tFoo 1\nHide\n\tClass \"a\"\n");
+        assert_eq!(iter.next().unwrap().block, None);
+        let show = iter.next().unwrap();
+        assert_eq!(show.block, Some(mode_parsing::Token::Show));
+        assert_eq!(iter.diagnostics()[0].message, "unknown keyword `Foo`");
+        assert_eq!(iter.take_diagnostics().len(), 1);
+        let hide = iter.next().unwrap();
+        assert_eq!(hide.keywords[0].token, mode_parsing::Token::Class);
+        assert!(iter.next().is_none());
+        assert!(iter.next().is_none());
+        assert!(iter.diagnostics().is_empty());
+
+        let first_hide = mode_parsing::BlockIter::new(filter_file)
+            .position(|b| b.block == Some(mode_parsing::Token::Hide));
+        assert!(first_hide.is_some());
+    }
+
+    #[test]
+    fn test_borrowed_values() {
+        let output = {
+            let source = String::from("Show # $tier->t1\n\tBaseType == \"Vaal Regalia\" # note\n");
+            let output = mode_parsing::parse(&source);
+            let keyword = &output.blocks[1].keywords[0];
+            assert!(matches!(keyword.value[0].value, Cow::Borrowed(_)));
+            assert!(matches!(
+                keyword.value[0].token,
+                mode_parsing::Token::Quotes(Cow::Borrowed(_))
+            ));
+            assert!(matches!(
+                keyword.annotation.as_ref().unwrap().value,
+                Cow::Borrowed(_)
+            ));
+            output.into_owned()
+        };
+        let keyword = &output.blocks[1].keywords[0];
+        assert_eq!(keyword.value[0].value, "\"Vaal Regalia\"");
+        assert!(matches!(keyword.value[0].value, Cow::Owned(_)));
+        assert_eq!(output.blocks[1].tags.get("tier"), Some("t1"));
+    }
+
+    #[test]
+    fn test_incremental_reparse() {
+        let source = include_str!("../src/test_filters/filter.filter");
+        let at = |text: &str| source.find(text).unwrap();
+        let edits = vec![
+            TextEdit::new(
+                at("SetFontSize 45")..at("SetFontSize 45") + 14,
+                "SetFontSize 40",
+            ),
+            TextEdit::new(at("Show")..at("Show") + 4, "Hide"),
+            TextEdit::new(at("Show")..at("Show"), "Show\n\tClass \"Rings\"\n"),
+            TextEdit::new(at("\nShow")..at("\nShow") + 1, " "),
+            TextEdit::new(at("\nShow")..at("\nShow") + 1, " # "),
+            TextEdit::new(at("Hide")..at("Hide") + 4, "Hid"),
+            TextEdit::new(at("\nHide") + 5..at("\nHide") + 5, "x"),
+            TextEdit::new(at("Show")..source.len() / 2, ""),
+            TextEdit::new(0..0, "Class \"Rings\"\n"),
+            TextEdit::new(source.len()..source.len(), "Show\n\tContinue"),
+        ];
+        for edit in edits {
+            let mut output = mode_parsing::parse(source).into_owned();
+            let edited = edit.apply(source);
+            let reparsed = incremental::reparse(&mut output, &edited, &edit, Default::default());
+            let expected = mode_parsing::parse(&edited);
+            assert_eq!(output, expected, "{:?}", edit);
+            assert!(reparsed.len() < output.blocks.len() / 2 + 2);
+        }
+    }
+
+    #[test]
+    fn test_source_map() {
+        let source = "Show\n\tBaseType \"Größe 🗡\" Foo\n";
+        let index = LineIndex::new(source);
+        assert_eq!(index.line_count(), 3);
+        let offset = source.find("Foo").unwrap();
+        let location = index.location(source, offset);
+        assert_eq!((location.line, location.column), (2, 21));
+        assert_eq!(location.utf16_column, 21);
+        assert_eq!(index.offset(source, 2, 21), Some(offset));
+        assert_eq!(index.offset_utf16(source, 2, 21), Some(offset));
+        assert_eq!(index.offset_utf16(source, 2, 18), None);
+        assert_eq!(index.offset(source, 1, 5), Some(4));
+        assert_eq!(index.offset(source, 1, 6), None);
+        assert_eq!(index.offset(source, 4, 1), None);
+
+        let mut map = SourceMap::default();
+        let base = map.add("base.filter", "Show\n\tClass \"Rings\"\n");
+        let extra = map.add("extra.filter", "Hide\n\tBogus 1\n");
+        assert_eq!((base, extra), (FileId(0), FileId(1)));
+        let combined = map.combined();
+        let output = mode_parsing::parse(&combined);
+        let diagnostic = map.relocate(output.diagnostics[0].clone());
+        assert_eq!(diagnostic.file, Some(extra));
+        assert_eq!(diagnostic.span, 6..11);
+        assert_eq!(
+            map.display(&diagnostic),
+            "extra.filter:2:2: error: unknown keyword `Bogus`"
+        );
+        let span = map.lookup(output.blocks[1].bspan.clone().unwrap());
+        assert_eq!(span.file, base);
+        assert_eq!(map.location(&span).line, 1);
+
+        let mut map = SourceMap::default();
+        map.add("a.filter", "Show\n\tClass \"a\" # no newline");
+        let b = map.add("b.filter", "Hide\n\tBogus 1\n");
+        let combined = map.combined();
+        assert_eq!(
+            combined,
+            "Show\n\tClass \"a\" # no newline\nHide\n\tBogus 1\n"
+        );
+        let output = mode_parsing::parse(&combined);
+        assert_eq!(output.blocks.len(), 3);
+        let diagnostic = map.relocate(output.diagnostics[0].clone());
+        assert_eq!(diagnostic.file, Some(b));
+        assert_eq!(diagnostic.span, 6..11);
+        let span = map.lookup(output.blocks[2].bspan.clone().unwrap());
+        assert_eq!((span.file, span.span), (b, 0..4));
+    }
+
+    #[test]
+    fn test_comment_at_end_of_file() {
+        let output = mode_parsing::parse("Show # $tier->t1");
+        assert_eq!(output.blocks[1].tags.get("tier"), Some("t1"));
+        let output = mode_parsing::parse("Show\n\tClass \"a\" # note # more");
+        let keyword = &output.blocks[1].keywords[0];
+        assert_eq!(keyword.annotation.as_ref().unwrap().value, "note # more");
+        assert!(mode_parsing::parse("#").diagnostics.is_empty());
+        assert_eq!(
+            cst::parse_cst("Show\n# end").parse(),
+            mode_parsing::parse("Show\n# end")
+        );
+    }
+
+    #[test]
+    fn test_imports() {
+        let output =
+            mode_parsing::parse("Import \"sounds.filter\" Optional\nShow\n\tClass \"Rings\"\n");
+        let import = output.blocks[1].import.as_ref().unwrap();
+        assert_eq!(
+            (import.path.as_ref(), import.optional),
+            ("sounds.filter", true)
+        );
+        assert_eq!(import.path_span, Some(7..22));
+        assert!(output.diagnostics.is_empty());
+        assert_eq!(
+            output.blocks[1].to_string(),
+            "Import \"sounds.filter\" Optional\n"
+        );
+        let errors = mode_parsing::parse("Import\n\tClass \"a\"\nImport Optional\n").diagnostics;
+        let messages: Vec<_> = errors.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            [
+                "`Import` needs a quoted path",
+                "`Class` outside of a Show or Hide block",
+                "`Import` takes a quoted path and an optional `Optional`",
+                "`Import` needs a quoted path",
+            ]
+        );
+        let errors = mode_parsing::parse("Show\nImport").diagnostics;
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].message, "`Import` needs a quoted path");
+        assert_eq!(cst::parse_cst("Show\nImport").parse().diagnostics, errors);
+        for source in [
+            "Import Hide x",
+            "Import \"a\" Show 5",
+            "Show\nImport \"a.filter\" Hide \"b\"",
+        ] {
+            let output = mode_parsing::parse(source);
+            assert!(output.has_errors());
+            assert_eq!(cst::parse_cst(source).parse(), output);
+        }
+        let errors = mode_parsing::parse("Import Hide").diagnostics;
+        assert_eq!(errors[0].message, "`Import` needs a quoted path");
+        let mut previous = mode_parsing::parse("Show\n").into_owned();
+        let edit = TextEdit::new(5..5, "Import");
+        incremental::reparse(
+            &mut previous,
+            "Show\nImport",
+            &edit,
+            ParseOptions::default(),
+        );
+        assert_eq!(previous, mode_parsing::parse("Show\nImport"));
+        let source = "Import \"a.filter\"\nShow\n\tClass \"Rings\"\n";
+        assert!(format::is_formatted(source));
+        assert_eq!(cst::parse_cst(source).blocks.len(), 3);
+
+        let files: HashMap<PathBuf, String> = [
+            ("team/base.filter", "Show\n\tClass \"Rings\"\nImport \"sub/overrides.filter\"\nImport \"missing.filter\" Optional\nHide\n"),
+            ("team/sub/overrides.filter", "Show\n\tRarity Unique\nImport \"../sounds.filter\"\n"),
+            ("team/sounds.filter", "Show\n\tPlayAlertSound 1\nImport \"base.filter\"\n"),
+        ]
+        .iter()
+        .map(|(path, source)| (PathBuf::from(path), source.to_string()))
+        .collect();
+        let resolved = imports::resolve(
+            Path::new("team/./base.filter"),
+            &files,
+            ParseOptions::default(),
+        );
+        let origins: Vec<_> = resolved
+            .blocks
+            .iter()
+            .map(|b| (b.file, b.block.bspan.clone().unwrap()))
+            .collect();
+        assert_eq!(
+            origins,
+            [
+                (FileId(0), 0..4),
+                (FileId(1), 0..4),
+                (FileId(2), 0..4),
+                (FileId(0), 83..87)
+            ]
+        );
+        assert_eq!(resolved.sources.file(FileId(2)).name, "team/sounds.filter");
+        let diagnostic = &resolved.diagnostics[0];
+        assert_eq!(diagnostic.file, Some(FileId(2)));
+        assert_eq!(
+            resolved.sources.display(diagnostic),
+            "team/sounds.filter:3:8: error: import cycle: team/base.filter -> team/sub/overrides.filter -> team/sounds.filter -> team/base.filter"
+        );
+        assert_eq!(resolved.diagnostics.len(), 1);
+
+        let resolved =
+            imports::resolve(Path::new("nowhere.filter"), &files, ParseOptions::default());
+        assert!(resolved.has_errors());
+
+        let mut files = HashMap::new();
+        files.insert(
+            PathBuf::from("a.filter"),
+            "Import \"\"\nImport\n".to_string(),
+        );
+        let resolved = imports::resolve(Path::new("a.filter"), &files, ParseOptions::default());
+        let messages: Vec<_> = resolved
+            .diagnostics
+            .iter()
+            .map(|d| (d.message.as_str(), d.span.clone()))
+            .collect();
+        assert_eq!(
+            messages,
+            [
+                ("`Import` needs a quoted path", 10..16),
+                ("empty import path", 7..9)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_encodings() {
+        let crlf = "Show # $tier->t1\r\n\tClass \"Rings\" # note\r\n\tSetFontSize 40\r\n";
+        let output = mode_parsing::parse(crlf);
+        assert!(output.diagnostics.is_empty());
+        assert_eq!(output.blocks[1].tags.get("tier"), Some("t1"));
+        let annotation = output.blocks[1].keywords[0].annotation.as_ref().unwrap();
+        assert_eq!(annotation.value, "note");
+        assert_eq!(cst::parse_cst(crlf).to_string(), crlf);
+        assert_eq!(
+            format::format("Show\r\n  Class Rings\r\n"),
+            "Show\r\n\tClass \"Rings\"\r\n"
+        );
+        let unterminated = "Show\r\n\tClass \"Rings\r\n";
+        let errors = mode_parsing::parse(unterminated).diagnostics;
+        assert_eq!(errors[0].message, "unterminated string");
+        assert_eq!(&unterminated[errors[0].span.clone()], "\"Rings");
+        assert_eq!(cst::parse_cst(unterminated).parse().diagnostics, errors);
+
+        let format = TextFormat {
+            encoding: Encoding::Utf16Le,
+            bom: true,
+            line_ending: LineEnding::CrLf,
+        };
+        let text = "Show\n\tBaseType \"Größe\"\n\tBogus 1\n";
+        let bytes = format.encode(text);
+        let decoded = encoding::decode(&bytes).unwrap();
+        assert_eq!((decoded.text.as_str(), decoded.format), (text, format));
+        assert_eq!(decoded.encode(&decoded.text), bytes);
+        let output = mode_parsing::parse(&decoded.text);
+        let span = decoded.original_span(output.diagnostics[0].span.clone());
+        assert_eq!(span, 54..64);
+        assert_eq!(encoding::decode(&bytes[span]).unwrap().text, "Bogus");
+
+        let utf8 = encoding::decode(b"\xef\xbb\xbfShow\r\n\tClass \"a\"\n\tWidth 1\n").unwrap();
+        assert_eq!(utf8.format.encoding, Encoding::Utf8);
+        assert!(utf8.format.bom);
+        assert_eq!(utf8.format.line_ending, LineEnding::Lf);
+        assert_eq!(utf8.original_span(4..5), 7..9);
+        assert_eq!(utf8.original_offset(utf8.text.len()), 29);
+
+        let mixed = b"Show\r\n\tClass  Rings\n\tWidth 1\r\n# end\n";
+        let decoded = encoding::decode(mixed).unwrap();
+        assert_eq!(decoded.encode(&decoded.text), mixed);
+        let formatted = format::format(&decoded.text);
+        assert_eq!(
+            decoded.encode(&formatted),
+            b"Show\r\n\tClass \"Rings\"\n\tWidth 1\r\n# end\n"
+        );
+        assert_eq!(decoded.encode("Show\n\n\n\n\n"), b"Show\r\n\n\r\n\n\n");
+        let readme = encoding::decode(include_bytes!("../../README.md")).unwrap();
+        assert_eq!(readme.format.encoding, Encoding::Utf16Le);
+        assert!(readme.text.starts_with("# poe_filter"));
+        let error = encoding::decode(b"Show\n\xff").unwrap_err();
+        assert_eq!(error.to_string(), "invalid UTF-8 at byte 5");
+    }
+
+    #[test]
+    fn test_evaluate() {
+        let filter = mode_parsing::parse(
+            "Show
+\tHasExplicitMod >= 2 \"of Haast\" \"Tyrannical\"
+Hide
+\tClass \"Bows\" \"Wands\"
+\tItemLevel < 75
+Show
+\tBaseType == \"Exalted Orb\"
+\tSetFontSize 45
+Show
+\tSocketGroup >= 5RRG
+\tRarity <= Rare
+\tContinue
+Hide
+\tLinkedSockets 6
+\tCorrupted True
+\tBlightedMap False
+Show
+\tClass ! \"Currency\"
+\tHasInfluence == Shaper Elder
+Hide
+\tBaseType \"Orb\"
+",
+        );
+        let blocks = &filter.blocks;
+        assert!(filter.diagnostics.is_empty());
+        assert_eq!(
+            blocks[1].conditions(),
+            [Condition::HasExplicitMod {
+                exact: false,
+                count: Some(2),
+                names: vec!["of Haast".to_string(), "Tyrannical".to_string()],
+            }]
+        );
+        let verdict = |item: &Item| (evaluate(blocks, item).visible, evaluate(blocks, item).block);
+
+        let mut bow = Item::new("Bows", "Thicket Bow");
+        bow.item_level = 60;
+        assert_eq!(verdict(&bow), (false, Some(2)));
+        bow.item_level = 84;
+        assert_eq!(verdict(&bow), (true, None));
+        bow.explicit_mods = vec!["Tyrannical".to_string(), "of the Haast".to_string()];
+        assert_eq!(verdict(&bow), (true, None));
+        bow.explicit_mods[1] = "of haast".to_string();
+        assert_eq!(verdict(&bow), (true, Some(1)));
+
+        assert_eq!(
+            verdict(&Item::new("Currency", "Exalted Orb")),
+            (true, Some(3))
+        );
+        assert_eq!(
+            verdict(&Item::new("Currency", "Chaos Orb")),
+            (false, Some(7))
+        );
+        assert_eq!(
+            verdict(&Item::new("Currency", "exalted orb")),
+            (true, Some(3))
+        );
+
+        let mut armour = Item::new("Body Armours", "Astral Plate");
+        armour.sockets = "R-R-G-B-B-R".to_string();
+        armour.rarity = Rarity::Rare;
+        let result = evaluate(blocks, &armour);
+        assert_eq!((result.visible, result.block), (true, Some(4)));
+        armour.corrupted = true;
+        let result = evaluate(blocks, &armour);
+        assert_eq!((result.visible, result.matched), (false, vec![4, 5]));
+        armour.sockets = "R-R-G-B B-R".to_string();
+        armour.influences = vec![Influence::Shaper];
+        assert_eq!(verdict(&armour), (true, None));
+        armour.influences.push(Influence::Elder);
+        assert_eq!(verdict(&armour), (true, Some(6)));
+        armour.rarity = Rarity::Unique;
+        armour.sockets = "R-R-G-B-B-R".to_string();
+        assert_eq!(verdict(&armour), (false, Some(5)));
+        assert_eq!(armour.links(), 6);
+
+        let filter = mode_parsing::parse(
+            "Show\n\tClass \"Currency\"\n\tPlayAlertSound 3 301\n\tSetTextColor 255 255 255 256\nHide\n",
+        );
+        let result = evaluate(&filter.blocks, &Item::new("Currency", "Exalted Orb"));
+        assert_eq!((result.visible, result.block), (true, Some(1)));
+    }
+
+    #[test]
+    fn test_resolve_style() {
+        let source = "Show
+\tClass \"Currency\"
+\tSetFontSize 40
+\tSetTextColor 200 200 200
+\tMinimapIcon 2 White Circle
+\tContinue
+Show
+\tBaseType \"Orb\"
+\tSetFontSize 45
+\tPlayAlertSound 2 200
+\tContinue
+Show
+\tBaseType == \"Exalted Orb\"
+\tMinimapIcon -1
+\tDisableDropSound
+Hide
+\tClass \"Currency\"
+\tPlayEffect Red Temp
+";
+        let filter = mode_parsing::parse(source);
+        let span = |text: &str| {
+            let start = source.find(text).unwrap();
+            Some(start..start + 4)
+        };
+        let style = resolve_style(&filter.blocks, &Item::new("Currency", "Exalted Orb"));
+        assert!(style.visible.value);
+        assert_eq!(style.visible.bspan, span("Show\n\tBaseType =="));
+        let font_size = style.font_size.unwrap();
+        assert_eq!(
+            (font_size.value, font_size.bspan),
+            (45, span("Show\n\tBaseType \"Orb"))
+        );
+        let text_color = style.text_color.unwrap();
+        assert_eq!(text_color.value, Rgba::new(200, 200, 200));
+        assert_eq!(text_color.bspan, span("Show"));
+        let sound = style.sound.unwrap().value;
+        assert_eq!(
+            sound,
+            Sound::Alert(AlertSound {
+                id: SoundId::Number(2),
+                volume: 200,
+                positional: false
+            })
+        );
+        assert_eq!(style.minimap_icon.unwrap().value.size, IconSize::Disabled);
+        assert!(!style.drop_sound.unwrap().value);
+        assert_eq!(style.border_color, None);
+        assert_eq!(style.beam, None);
+
+        let style = resolve_style(&filter.blocks, &Item::new("Currency", "Chaos Shard"));
+        assert!(!style.visible.value);
+        let beam = style.beam.unwrap();
+        assert_eq!(beam.value.color, Some(EffectColor::Red));
+        assert_eq!(beam.bspan, span("Hide"));
+        assert_eq!(style.font_size.unwrap().value, 40);
+        assert_eq!(
+            style.minimap_icon.unwrap().value.shape,
+            Some(IconShape::Circle)
+        );
+
+        let style = resolve_style(&filter.blocks, &Item::new("Bows", "Thicket Bow"));
+        assert_eq!((style.visible.value, style.visible.bspan), (true, None));
+        assert_eq!(style.font_size, None);
+    }
+
+    #[test]
+    fn test_explain() {
+        let source = "Show
+\tItemLevel >= 86
+\tClass \"Bows\" \"Wands\"
+Show
+\tClass \"Currency\"
+\tSetFontSize 40
+\tContinue
+Hide
+\tBaseType \"Orb\"
+\tStackSize < 5
+Show
+\tBaseType == \"Exalted Orb\"
+";
+        let filter = mode_parsing::parse(source);
+        let item = Item::new("Currency", "Exalted Orb");
+        let explanation = explain(&filter.blocks, &item);
+        assert_eq!(explanation.verdict.matched, [2, 3]);
+        let first = &explanation.blocks[0].conditions[0];
+        assert_eq!(first.condition, "ItemLevel >= 86");
+        assert_eq!((first.actual.as_str(), first.passed), ("1", false));
+        assert_eq!(first.span, Some(6..15));
+        assert_eq!(
+            explanation.report(source),
+            "1:1: `Show` does not match
+\t2:2: fail `ItemLevel >= 86`, item has 1
+\t3:2: fail `Class \"Bows\" \"Wands\"`, item has \"Currency\"
+4:1: `Show` matches and continues
+\t5:2: pass `Class \"Currency\"`, item has \"Currency\"
+8:1: `Hide` matches and decides
+\t9:2: pass `BaseType \"Orb\"`, item has \"Exalted Orb\"
+\t10:2: pass `StackSize < 5`, item has 1
+item is hidden
+"
+        );
+        let mut item = Item::new("Currency", "Exalted Orb");
+        item.stack_size = 10;
+        let explanation = explain(&filter.blocks, &item);
+        assert_eq!(explanation.blocks.len(), 4);
+        assert!(explanation.verdict.visible);
+        let report = explanation.report(source);
+        assert!(report.ends_with("11:1: `Show` matches and decides\n\t12:2: pass `BaseType == \"Exalted Orb\"`, item has \"Exalted Orb\"\nitem is shown\n"));
+    }
+
+    /// Filter-like text made of every keyword the registry knows and the
+    /// pieces values are made of, with the keywords that start or end blocks
+    /// picked more often.
+    fn filter_like_text() -> impl Strategy<Value = String> {
+        let names: Vec<_> = keywords::KEYWORDS.iter().map(|k| k.name).collect();
+        let piece = prop_oneof![
+            prop::sample::select(names).prop_map(str::to_string),
+            "Show|Hide|Continue|Import",
+            "[ \t]",
+            "#|\"|\\$|->|==|!=|<|>=|!|[0-9]{1,3}|[a-z]{1,5}|[ \t]|\r?\n",
+        ];
+        prop::collection::vec(piece, 0..64).prop_map(|pieces| pieces.concat())
+    }
+
+    proptest! {
+        #[test]
+        fn test_parse_any_bytes(bytes in proptest::collection::vec(any::<u8>(), 0..512)) {
+            let source = String::from_utf8_lossy(&bytes);
+            let output = mode_parsing::parse(&source);
+            for block in output.blocks.iter() {
+                for keyword in block.keywords.iter() {
+                    let _ = keyword.statement();
+                }
+            }
+            prop_assert_eq!(cst::parse_cst(&source).to_string(), source.to_string());
+        }
+
+        #[test]
+        fn test_parse_filter_like_text(source in filter_like_text()) {
+            let output = mode_parsing::parse(&source);
+            prop_assert_eq!(cst::parse_cst(&source).parse(), output);
+            prop_assert!(format::is_formatted(&format::format(&source)));
+            let legacy = parse_legacy(&source);
+            for block in legacy.blocks.iter() {
+                for keyword in block.keywords.iter() {
+                    let _ = keyword.statement();
+                }
+            }
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_json_round_trip() {
+        let filter_file = include_str!("../src/test_filters/filter.filter");
+        let output = mode_parsing::parse(filter_file);
+        let json = serde_json::to_string(&output).unwrap();
+        let imported: mode_parsing::ParseOutput = serde_json::from_str(&json).unwrap();
+        assert_eq!(imported, output);
+        assert_eq!(
+            printer::print(&imported.blocks),
+            printer::print(&output.blocks)
+        );
+
+        let output = mode_parsing::parse("Show\n\tSetFontSize 45\n");
+        let json = serde_json::to_value(&output.blocks[1].keywords[0]).unwrap();
+        assert_eq!(json["token"], "SetFontSize");
+        assert_eq!(json["value"][0]["token"]["Numbers"], "45");
+        let action = serde_json::to_value(&output.blocks[1].actions()[0]).unwrap();
+        assert_eq!(action, serde_json::json!({ "SetFontSize": 45 }));
+    }
+
+    #[cfg(feature = "schema")]
+    #[test]
+    fn test_json_schema_is_published() {
+        let schema = serde_json::to_string_pretty(&mode_parsing::json_schema()).unwrap();
+        let published = include_str!("../schema/parse_output.json");
+        assert_eq!(published, schema + "\n");
+    }
+
+    #[test]
+    fn test_keyword_versions() {
+        let filter_file = include_str!("../src/test_filters/filter.filter");
+        let output = mode_parsing::parse(filter_file);
+        assert!(output.diagnostics.is_empty());
+        let blighted = output
+            .blocks
+            .iter()
+            .flat_map(|b| b.keywords.iter())
+            .filter(|k| k.token == mode_parsing::Token::BlightedMap);
+        assert_eq!(blighted.count(), 2);
+
+        let info = keywords::keyword_by_name("ArchnemesisMod").unwrap();
+        assert!(info.available_in(GameVersion::new(3, 17)));
+        assert!(!info.available_in(GameVersion::new(3, 18)));
+        assert!(!info.available_in(GameVersion::new(3, 16)));
+        assert_eq!(
+            keywords::keyword_info(&mode_parsing::Token::Class)
+                .unwrap()
+                .name,
+            "Class"
+        );
 
+        let source = "Show\n\tBlightedMap True\n\tScourged False\n\tTransfiguredGem True\n";
+        let options = |major, minor| ParseOptions {
+            target_version: Some(GameVersion::new(major, minor)),
+            ..Default::default()
+        };
+        let output = mode_parsing::parse_with(source, options(3, 17));
+        let messages: Vec<&str> = output
+            .diagnostics
+            .iter()
+            .map(|d| d.message.as_str())
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                "`Scourged` was removed in game version 3.17, the filter targets 3.17",
+                "`TransfiguredGem` needs game version 3.23 or later, the filter targets 3.17",
+            ]
+        );
+        assert_eq!(output.diagnostics[0].line, 3);
+        assert!(
+            mode_parsing::parse_with(source, options(3, 16))
+                .diagnostics
+                .len()
+                == 1
+        );
+        assert!(mode_parsing::parse(source).diagnostics.is_empty());
     }
 
     // #[test]
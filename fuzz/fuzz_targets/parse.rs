@@ -0,0 +1,16 @@
+#![no_main]
+use filter_lib::{cst, format, mode_parsing};
+use libfuzzer_sys::fuzz_target;
+
+// Run with `cargo +nightly fuzz run parse` from the repository root.
+fuzz_target!(|data: &[u8]| {
+    let source = String::from_utf8_lossy(data);
+    let output = mode_parsing::parse(&source);
+    for block in output.blocks.iter() {
+        for keyword in block.keywords.iter() {
+            let _ = keyword.statement();
+        }
+    }
+    assert_eq!(cst::parse_cst(&source).to_string(), source);
+    let _ = format::format(&source);
+});